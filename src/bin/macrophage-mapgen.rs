@@ -0,0 +1,76 @@
+//! Generates symmetric maps into `assets/maps`
+//!
+//! Usage: `macrophage-mapgen <count> [--seed N] [--players N] [--nodes N]
+//! [--symmetry rotational|mirror] [--replicators N] [--generators N] [--recombinators N]`
+//!
+//! Writes `count` maps using consecutive seeds starting at `--seed`, each named
+//! `generated-<symmetry>-<players>p-<seed>.json`
+
+use std::fs;
+use std::process::exit;
+use std::str::FromStr;
+
+use macrophage::game::mapgen::{generate_map, MapGenSettings, Symmetry};
+
+const USAGE: &str = "Usage: macrophage-mapgen <count> [--seed N] [--players N] [--nodes N] \
+[--symmetry rotational|mirror] [--replicators N] [--generators N] [--recombinators N]";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    exit(2);
+}
+
+fn parse<T: FromStr>(flag: &str, value: Option<String>) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| fail(&format!("{} expects a number", flag)))
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let count: u64 = parse("<count>", args.next());
+
+    let mut settings = MapGenSettings::default();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--seed" => settings.seed = parse(&flag, args.next()),
+            "--players" => settings.players = parse(&flag, args.next()),
+            "--nodes" => settings.nodes = parse(&flag, args.next()),
+            "--replicators" => settings.mix.replicators = parse(&flag, args.next()),
+            "--generators" => settings.mix.generators = parse(&flag, args.next()),
+            "--recombinators" => settings.mix.recombinators = parse(&flag, args.next()),
+            "--symmetry" => {
+                settings.symmetry = match args.next().as_deref() {
+                    Some("rotational") => Symmetry::Rotational,
+                    Some("mirror") => Symmetry::Mirror,
+                    _ => fail("--symmetry expects rotational or mirror"),
+                }
+            }
+            _ => fail(&format!("Unknown argument {}", flag)),
+        }
+    }
+
+    let first_seed = settings.seed;
+    for seed in first_seed..first_seed + count {
+        settings.seed = seed;
+        let map = match generate_map(&settings) {
+            Ok(map) => map,
+            Err(err) => {
+                eprintln!("Could not generate a map: {}", err);
+                exit(1);
+            }
+        };
+
+        let file_name = format!(
+            "generated-{:?}-{}p-{}.json",
+            settings.symmetry, settings.players, seed
+        )
+        .to_lowercase();
+        let path = format!("assets/maps/{}", file_name);
+        if let Err(err) = fs::write(&path, serde_json::to_string(&map).unwrap()) {
+            eprintln!("Could not write {}: {}", path, err);
+            exit(1);
+        }
+        println!("{}", path);
+    }
+}
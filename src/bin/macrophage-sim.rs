@@ -0,0 +1,102 @@
+//! Plays a scripted match without a window and prints the result as JSON
+//!
+//! Usage: `macrophage-sim <script.json>`
+//!
+//! Needs no window, GPU or audio device, build it without the `client` feature on
+//! headless machines: `cargo build --no-default-features --bin macrophage-sim`
+//!
+//! The script names a map in `assets/maps` and lists the moves each force submits
+//! during every mutation phase, one entry per round:
+//! `{ "map": "test_map.json", "rounds": [[{ "force": 0, "game_move": { ... } }], []] }`
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::exit;
+
+use macrophage::game::{
+    gamerunner::TransmissionEvents, map::load_map, simulation::Simulation, Force, GameMove,
+    GameState, PlayerActionError,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct Script {
+    map: String,
+    rounds: Vec<Vec<ScriptedMove>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ScriptedMove {
+    force: Force,
+    game_move: GameMove,
+}
+
+#[derive(Serialize)]
+struct RoundReport {
+    rejected: Vec<(ScriptedMove, PlayerActionError)>,
+    events: Vec<TransmissionEvents>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    rounds: Vec<RoundReport>,
+    victor: Option<Force>,
+    state: GameState,
+}
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: macrophage-sim <script.json>");
+            exit(2);
+        }
+    };
+
+    let script: Script = match File::open(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|file| {
+            serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())
+        }) {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("Could not read script {}: {}", path, err);
+            exit(1);
+        }
+    };
+
+    let map = match load_map(&script.map) {
+        Ok(map) => map,
+        Err(err) => {
+            eprintln!("Could not load map {}: {}", script.map, err);
+            exit(1);
+        }
+    };
+
+    let mut sim = Simulation::new(map);
+    let mut rounds = Vec::default();
+    for moves in script.rounds {
+        let mut rejected = Vec::default();
+        for scripted in moves {
+            if let Err(reason) = sim.submit(scripted.force, &scripted.game_move) {
+                rejected.push((scripted, reason));
+            }
+        }
+
+        rounds.push(RoundReport {
+            rejected,
+            events: sim.advance_round(),
+        });
+
+        if sim.victor().is_some() {
+            break;
+        }
+    }
+
+    let report = Report {
+        rounds,
+        victor: sim.victor(),
+        state: sim.state,
+    };
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{Force, GameState, NodeId, Vector, VectorSet};
+
+/// How many phases of local checksums are kept around to compare against late peers
+const CHECKSUM_HISTORY: usize = 64;
+
+/// 64 bit FNV-1a, stable across platforms, processes and compiler versions unlike `std`'s hasher
+#[derive(Clone, Copy)]
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_json<T: Serialize>(&mut self, value: &T) {
+        self.write(&serde_json::to_vec(value).expect("game state is always serializable"));
+    }
+}
+
+/// An order independent fingerprint of a [`GameState`], detailed enough to point at the
+/// first part of the state that differs between two clients
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct StateChecksum {
+    /// Transmission phases run to produce the state
+    pub phase: u32,
+    pub total: u64,
+    pub nodes: BTreeMap<NodeId, u64>,
+    pub vectors: Vec<Vector>,
+    /// Everything other than nodes and vectors, qubits, eliminated forces and map rules
+    pub other: u64,
+}
+
+/// The first difference found between two checksums
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Desync {
+    Node(NodeId),
+    Vector(Vector),
+    Other,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Desync::Node(id) => write!(f, "node {:?} differs", id),
+            Desync::Vector(vector) => write!(f, "vector {:?} exists on only one client", vector),
+            Desync::Other => write!(f, "qubits, eliminated forces or map rules differ"),
+        }
+    }
+}
+
+impl StateChecksum {
+    pub fn of(state: &GameState) -> Self {
+        let mut total = Fnv::default();
+
+        let mut nodes = BTreeMap::new();
+        for id in state.node_ids_in_order() {
+            let mut node = Fnv::default();
+            node.write_json(&state.nodes[&id]);
+            total.write(&node.0.to_le_bytes());
+            nodes.insert(id, node.0);
+        }
+
+        let vectors: Vec<Vector> = state.vectors.iter().copied().collect();
+        total.write_json(&vectors);
+
+        let rest = GameState {
+            nodes: BTreeMap::default(),
+            vectors: VectorSet::default(),
+            ..state.clone()
+        };
+        let mut other = Fnv::default();
+        other.write_json(&rest);
+        total.write(&other.0.to_le_bytes());
+
+        StateChecksum {
+            phase: state.transmission_phases,
+            total: total.0,
+            nodes,
+            vectors,
+            other: other.0,
+        }
+    }
+
+    /// The first node, then vector, that differs from `other`, `None` if they match
+    pub fn desync(&self, other: &StateChecksum) -> Option<Desync> {
+        if self.total == other.total {
+            return None;
+        }
+
+        let ids = self.nodes.keys().chain(other.nodes.keys());
+        if let Some(id) = ids
+            .filter(|id| self.nodes.get(id) != other.nodes.get(id))
+            .min()
+        {
+            return Some(Desync::Node(*id));
+        }
+
+        let vectors = self.vectors.iter().chain(other.vectors.iter());
+        if let Some(vector) = vectors
+            .filter(|vector| {
+                self.vectors.binary_search(vector).is_ok()
+                    != other.vectors.binary_search(vector).is_ok()
+            })
+            .min()
+        {
+            return Some(Desync::Vector(*vector));
+        }
+
+        Some(Desync::Other)
+    }
+}
+
+/// Checksums of the local state after each recent transmission phase, keyed by phase
+#[derive(Default)]
+pub struct ChecksumHistoryRes {
+    pub checksums: BTreeMap<u32, StateChecksum>,
+}
+
+impl ChecksumHistoryRes {
+    pub fn record(&mut self, checksum: StateChecksum) {
+        self.checksums.insert(checksum.phase, checksum);
+        while self.checksums.len() > CHECKSUM_HISTORY {
+            self.checksums.pop_first();
+        }
+    }
+}
+
+/// A checksum received from a peer, to be compared with the local state of the same phase
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PeerChecksumEvent {
+    pub peer: Force,
+    pub checksum: StateChecksum,
+}
+
+/// A peer computed a different state than this client
+#[derive(Clone, Debug)]
+pub struct DesyncDetected {
+    pub peer: Force,
+    pub phase: u32,
+    pub desync: Desync,
+}
+
+pub(crate) fn check_peer_checksums(
+    history: Res<ChecksumHistoryRes>,
+    mut peer_checksums: EventReader<PeerChecksumEvent>,
+    mut desyncs: EventWriter<DesyncDetected>,
+) {
+    for ev in peer_checksums.iter() {
+        let local = match history.checksums.get(&ev.checksum.phase) {
+            Some(local) => local,
+            None => continue,
+        };
+
+        if let Some(desync) = local.desync(&ev.checksum) {
+            error!(
+                "Desync with {:?} after transmission phase {}: {}",
+                ev.peer, ev.checksum.phase, desync
+            );
+            desyncs.send(DesyncDetected {
+                peer: ev.peer,
+                phase: ev.checksum.phase,
+                desync,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{Cell, NodeTenant, Occupant, PhageType};
+
+    use super::*;
+
+    fn triangle() -> GameState {
+        let mut map = GameState::new("Triangle".to_string(), 2);
+        let ids: Vec<_> = (0..3)
+            .map(|i| map.create_node(Force(0), Vec3::new(i as f32, 0., 0.)))
+            .collect();
+        map.add_vector(Vector::new(ids[0], ids[1])).unwrap();
+        map.add_vector(Vector::new(ids[1], ids[2])).unwrap();
+        map
+    }
+
+    #[test]
+    pub fn checksums_ignore_storage_order() {
+        let map = triangle();
+        let mut reordered = map.clone();
+        reordered.vectors = map.vectors.iter().rev().copied().collect();
+
+        assert_eq!(StateChecksum::of(&map), StateChecksum::of(&reordered));
+        assert_eq!(
+            StateChecksum::of(&map).desync(&StateChecksum::of(&reordered)),
+            None
+        );
+    }
+
+    #[test]
+    pub fn desyncs_point_at_the_first_difference() {
+        let map = triangle();
+
+        let mut occupied = map.clone();
+        occupied.nodes.get_mut(&NodeId(2)).unwrap().tenant = NodeTenant::Cell {
+            cell: Cell {
+                occupant: Some(Occupant(Force(1), PhageType::UV)),
+            },
+        };
+        assert_eq!(
+            StateChecksum::of(&map).desync(&StateChecksum::of(&occupied)),
+            Some(Desync::Node(NodeId(2)))
+        );
+
+        let mut connected = map.clone();
+        connected
+            .add_vector(Vector::new(NodeId(0), NodeId(2)))
+            .unwrap();
+        assert_eq!(
+            StateChecksum::of(&map).desync(&StateChecksum::of(&connected)),
+            Some(Desync::Vector(Vector::new(NodeId(0), NodeId(2))))
+        );
+
+        let mut richer = map.clone();
+        richer.grant_qubits(Force(1), 1);
+        assert_eq!(
+            StateChecksum::of(&map).desync(&StateChecksum::of(&richer)),
+            Some(Desync::Other)
+        );
+    }
+}
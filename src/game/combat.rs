@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use super::PhageType;
+
+/// Data driven rock-paper-scissors table deciding which phage type wins a contested cell.
+/// Stored alongside the map so every map can tune its own matchups
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct MatchupTable {
+    /// (victor, loser) pairs, any matchup not listed here is a tie
+    pub wins: Vec<(PhageType, PhageType)>,
+}
+
+impl Default for MatchupTable {
+    /// UV beats Electro, Electro beats Sonic, Sonic beats UV and every
+    /// specialised phage beats the generalist `Any`
+    fn default() -> Self {
+        MatchupTable {
+            wins: vec![
+                (PhageType::UV, PhageType::Electro),
+                (PhageType::Electro, PhageType::Sonic),
+                (PhageType::Sonic, PhageType::UV),
+                (PhageType::UV, PhageType::Any),
+                (PhageType::Electro, PhageType::Any),
+                (PhageType::Sonic, PhageType::Any),
+            ],
+        }
+    }
+}
+
+impl MatchupTable {
+    pub fn beats(&self, attacker: PhageType, defender: PhageType) -> bool {
+        self.wins.contains(&(attacker, defender))
+    }
+}
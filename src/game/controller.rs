@@ -24,6 +24,27 @@ pub enum PlayerAction {
     /// Triggers the Selected Recombinator
     TriggerRecombinator,
 
+    /// Done submitting moves for this mutation phase, used by turn based matches
+    EndTurn,
+
+    /// Pauses or resumes the game, votes to when playing over the network
+    TogglePause,
+
+    /// Advances a paused game by one phase
+    StepPhase,
+
+    /// Doubles the game speed
+    SpeedUp,
+
+    /// Halves the game speed
+    SlowDown,
+
+    /// Undoes the last change made in the map editor
+    Undo,
+
+    /// Reapplies the last change undone in the map editor
+    Redo,
+
     OpenOptionsMenu,
 
     HotKey1,
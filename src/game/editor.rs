@@ -0,0 +1,256 @@
+use std::fs;
+use std::io;
+
+use bevy::prelude::*;
+
+use super::{
+    Cell, Force, GameState, Generator, Nexus, NodeId, NodeTenant, PhageType, PlayerActionError,
+    Recombinator, RecombinatorEffect, RecombinatorTriggers, Replicator, Vector,
+};
+
+/// How many edits can be undone
+const UNDO_HISTORY: usize = 100;
+
+/// Which kind of tenant the editor places on new nodes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TenantKind {
+    Cell,
+    Replicator,
+    Nexus,
+    Generator,
+    Recombinator,
+}
+
+impl TenantKind {
+    pub const ALL: [TenantKind; 5] = [
+        TenantKind::Cell,
+        TenantKind::Replicator,
+        TenantKind::Nexus,
+        TenantKind::Generator,
+        TenantKind::Recombinator,
+    ];
+
+    /// A fresh tenant of this kind, replicators produce `phage`
+    pub fn tenant(&self, phage: PhageType) -> NodeTenant {
+        match self {
+            TenantKind::Cell => NodeTenant::Cell {
+                cell: Cell { occupant: None },
+            },
+            TenantKind::Replicator => NodeTenant::Replicator {
+                replicator: Replicator {
+                    output: phage,
+                    speed: 3,
+                    phases_waited: 0,
+                },
+            },
+            TenantKind::Nexus => NodeTenant::Nexus {
+                nexus: Nexus::default(),
+            },
+            TenantKind::Generator => NodeTenant::Generator {
+                generator: Generator {
+                    amt: 50,
+                    speed: 1,
+                    occupant: None,
+                },
+            },
+            TenantKind::Recombinator => NodeTenant::Recombinator {
+                recombinator: Recombinator {
+                    trigger: RecombinatorTriggers::NumberOfTransmissionPhases { phases: 5 },
+                    effect: RecombinatorEffect::GiveOccupierQubits { amt: 10 },
+                    occupant: None,
+                    observed: Default::default(),
+                },
+            },
+        }
+    }
+}
+
+/// A map being edited along with the history needed to undo and redo changes to it.
+/// Every edit either succeeds completely or leaves the map untouched
+#[derive(Clone, Debug)]
+pub struct MapEditor {
+    pub state: GameState,
+    undo: Vec<GameState>,
+    redo: Vec<GameState>,
+}
+
+impl MapEditor {
+    pub fn new(state: GameState) -> Self {
+        MapEditor {
+            state,
+            undo: Vec::default(),
+            redo: Vec::default(),
+        }
+    }
+
+    /// Applies `edit` to a copy of the map, keeping it only if the edit succeeds
+    pub fn edit<T>(
+        &mut self,
+        edit: impl FnOnce(&mut GameState) -> Result<T, PlayerActionError>,
+    ) -> Result<T, PlayerActionError> {
+        let mut next = self.state.clone();
+        let result = edit(&mut next)?;
+        next.refresh_spawn_slots();
+
+        self.undo.push(std::mem::replace(&mut self.state, next));
+        if self.undo.len() > UNDO_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        Ok(result)
+    }
+
+    pub fn place_node(&mut self, force: Force, tenant: NodeTenant, position: Vec3) -> NodeId {
+        self.edit(|state| {
+            let id = state.create_node(force, position);
+            state.nodes.get_mut(&id).unwrap().tenant = tenant;
+            Ok(id)
+        })
+        .expect("placing a node cannot fail")
+    }
+
+    /// Changes the force and tenant of an existing node
+    pub fn repaint_node(
+        &mut self,
+        id: NodeId,
+        force: Force,
+        tenant: NodeTenant,
+    ) -> Result<(), PlayerActionError> {
+        self.edit(|state| {
+            let node = state
+                .nodes
+                .get_mut(&id)
+                .ok_or(PlayerActionError::NodeIdDoesNotExist(id))?;
+            node.force = force;
+            node.tenant = tenant;
+            Ok(())
+        })
+    }
+
+    pub fn delete_node(&mut self, id: NodeId) -> Result<(), PlayerActionError> {
+        self.edit(|state| state.remove_node(id).map(|_| ()))
+    }
+
+    pub fn connect(&mut self, from: NodeId, to: NodeId) -> Result<(), PlayerActionError> {
+        self.edit(|state| state.add_vector(Vector::new(from, to)))
+    }
+
+    pub fn disconnect(&mut self, vector: Vector) -> Result<(), PlayerActionError> {
+        self.edit(|state| state.remove_vector(vector))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(previous) = self.undo.pop() {
+            self.redo.push(std::mem::replace(&mut self.state, previous));
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(next) = self.redo.pop() {
+            self.undo.push(std::mem::replace(&mut self.state, next));
+        }
+    }
+
+    /// The node closest to `position` on the ground plane, within `radius`
+    pub fn node_at(&self, position: Vec3, radius: f32) -> Option<NodeId> {
+        self.state
+            .nodes
+            .values()
+            .map(|node| (node.id, ground_distance(node.position, position)))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id)
+    }
+
+    /// The vector passing closest to `position` on the ground plane, within `radius`
+    pub fn vector_at(&self, position: Vec3, radius: f32) -> Option<Vector> {
+        let point = ground(position);
+        self.state
+            .vectors
+            .iter()
+            .filter_map(|vector| {
+                let start = ground(self.state.nodes.get(&vector.0)?.position);
+                let end = ground(self.state.nodes.get(&vector.1)?.position);
+                let along = (point - start).dot(end - start) / (end - start).length_squared();
+                let closest = start + (end - start) * along.clamp(0., 1.);
+                Some((*vector, closest.distance(point)))
+            })
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(vector, _)| vector)
+    }
+
+    /// Writes the map to `assets/maps` in the same format the game loads
+    pub fn save(&self, file_name: &str) -> io::Result<()> {
+        fs::write(
+            format!("assets/maps/{}", file_name),
+            serde_json::to_string(&self.state)?,
+        )
+    }
+}
+
+fn ground(position: Vec3) -> Vec2 {
+    Vec2::new(position.x, position.z)
+}
+
+fn ground_distance(a: Vec3, b: Vec3) -> f32 {
+    ground(a).distance(ground(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::map::read_map;
+
+    use super::*;
+
+    #[test]
+    pub fn edits_can_be_undone_and_redone() {
+        let mut editor = MapEditor::new(GameState::new("Edited".to_string(), 2));
+        let nexus = editor.place_node(
+            Force(0),
+            TenantKind::Nexus.tenant(PhageType::Any),
+            Vec3::ZERO,
+        );
+        let cell = editor.place_node(
+            Force(1),
+            TenantKind::Cell.tenant(PhageType::Any),
+            Vec3::new(4., 0., 0.),
+        );
+        editor.connect(nexus, cell).unwrap();
+        assert_eq!(editor.node_at(Vec3::new(3.5, 2., 0.), 1.), Some(cell));
+        assert_eq!(
+            editor.vector_at(Vec3::new(2., 0., 0.5), 1.),
+            Some(Vector::new(nexus, cell))
+        );
+
+        //Failed edits leave no history behind
+        assert!(editor.connect(cell, nexus).is_err());
+        editor.delete_node(nexus).unwrap();
+        assert!(editor.state.vectors.is_empty());
+
+        editor.undo();
+        assert!(editor.state.vector_exists(Vector::new(nexus, cell)));
+        editor.undo();
+        assert!(editor.state.vectors.is_empty());
+        editor.redo();
+        assert!(editor.state.vector_exists(Vector::new(nexus, cell)));
+
+        //Editing after an undo drops the redo history
+        editor.undo();
+        editor
+            .repaint_node(cell, Force(0), TenantKind::Generator.tenant(PhageType::Any))
+            .unwrap();
+        assert!(!editor.can_redo());
+
+        let json = serde_json::to_vec(&editor.state).unwrap();
+        assert_eq!(read_map(&json[..]).unwrap(), editor.state);
+    }
+}
@@ -1,9 +1,26 @@
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use super::{Force, NodeId, PhageType, Recombinator};
+use super::{
+    checksum::{
+        check_peer_checksums, ChecksumHistoryRes, DesyncDetected, PeerChecksumEvent, StateChecksum,
+    },
+    map::process_map_mutations,
+    matchconfig::begin_match,
+    phasedriver::PhaseDriverPlugin,
+    replay::ReplayPlugin,
+    runnercontrol::RunnerControlPlugin,
+    simulation::submit_move,
+    transmission::{PendingOrders, Transmission},
+    victory::victor,
+    visibility::{update_fog_of_war, FogOfWarRes},
+    Force, GameMove, GameState, LevelManagerRes, LocalPlayerRes, MutationFailed, NodeId, PhageType,
+    PlayerActionError, PlayerMutationEvent, Recombinator,
+};
 
 /// The order in which game state is processed during the transmission phase
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TransmissionSubPhase {
     /// Distribute any qubits from enriched nodes to the occupying player
     DistributeQubits,
@@ -17,16 +34,32 @@ pub enum TransmissionSubPhase {
     /// A cell is considered contested when phage from opposing Forces
     /// are attempting to occupy a cell at the same time. If two allied cells
     /// attempt to move into the same cell the cell that is farthest from the
-    /// nexus will move while the other cell will stay put (Or go to next best?)
+    /// nexus will move while the other cell will stay put
     Movement,
 
     /// When two Phage on opposing forces are attempting to occupy the same cell
     /// Resolve via combat with the victor occupying the cell
     Combat,
+
+    /// Replicators that have waited long enough produce a phage into a free
+    /// neighboring cell, the lowest node id is picked when several are free
+    Replicate,
+}
+
+impl TransmissionSubPhase {
+    /// Every sub phase in the order it is executed
+    pub const ORDER: [TransmissionSubPhase; 5] = [
+        TransmissionSubPhase::DistributeQubits,
+        TransmissionSubPhase::ReCombinate,
+        TransmissionSubPhase::Movement,
+        TransmissionSubPhase::Combat,
+        TransmissionSubPhase::Replicate,
+    ];
 }
 
 /// Events related to updates to game state that occur during the transmission
 /// phase. Intended to be consumed by other systems to display visual cues
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum TransmissionEvents {
     /// A phage moved from one cell to another
     PhageMoved { from: NodeId, to: NodeId },
@@ -38,12 +71,49 @@ pub enum TransmissionEvents {
         lose: (Force, PhageType),
     },
 
+    /// A replicator produced a new phage
+    PhageReplicated {
+        replicator: NodeId,
+        cell: NodeId,
+        phage: (Force, PhageType),
+    },
+
+    /// An opposing phage threw itself at a nexus, `health` is what the nexus has left
+    NexusDamaged {
+        nexus: NodeId,
+        attacker: (Force, PhageType),
+        health: u32,
+    },
+
+    /// A nexus ran out of health and now belongs to the force that dealt the final blow
+    NexusCaptured {
+        nexus: NodeId,
+        from: Force,
+        by: Force,
+    },
+
+    /// A force lost its last nexus and is out of the game
+    ForceEliminated { force: Force },
+
+    /// A phage was destroyed outside of combat, for example by a recombinator
+    PhageDestroyed {
+        cell: NodeId,
+        phage: (Force, PhageType),
+    },
+
     /// A recombinator triggered
     RecombinatorTriggered {
         cell: NodeId,
         recombinator: Recombinator,
     },
 
+    /// Qubits offered by one force were handed to another
+    QubitsTraded {
+        source: Force,
+        dest: Force,
+        qty: u32,
+    },
+
     /// Qubits were distributed to a player from either a Recombinator or a node
     QubitsDistributed {
         recipient: Force,
@@ -57,15 +127,56 @@ pub enum TransmissionEvents {
 /// the game is advanced. For example on a timer or turn based
 pub struct AdvanceGamePhaseEvent {}
 
+/// Runs the next [`TransmissionSubPhase`] of a transmission phase that was entered
+/// while paused. Ignored when no transmission is in progress
+pub struct AdvanceSubPhaseEvent {}
+
+/// A move submitted by a force, applied or queued by the game runner
+#[derive(Clone, Debug)]
+pub struct GameMoveEvent {
+    pub force: Force,
+    pub game_move: GameMove,
+}
+
+/// Sent when the game runner applies or queues a move, in the order they were accepted
+#[derive(Clone, Debug)]
+pub struct GameMoveAccepted {
+    pub force: Force,
+    pub game_move: GameMove,
+}
+
+/// Sent back when the game runner refuses a [`GameMoveEvent`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GameMoveRejected {
+    pub force: Force,
+    pub game_move: GameMove,
+    pub reason: PlayerActionError,
+}
+
 /// A way for the GameRunner to notify other systems about events related to the game as a whole
+#[derive(Clone, PartialEq, Debug)]
 pub enum GameRunnerEvent {
     GameBegun,
-    GameCompleted { victor: Force },
-    PhaseEntered { phase: GamePhase },
-    PhaseExited { phase: GamePhase },
+    GameCompleted {
+        victor: Force,
+    },
+    PhaseEntered {
+        phase: GamePhase,
+    },
+    PhaseExited {
+        phase: GamePhase,
+    },
+    /// Every sub phase of the current transmission phase has run
+    TransmissionResolved,
+    Paused,
+    Resumed,
+    SpeedChanged {
+        speed: f32,
+    },
 }
 
 /// Procedure for running gameplay
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum GamePhase {
     /// Players can submit mutations to the board which take effect immediately
     /// One mutation can be submitted per tile, per mutation phase, per player.
@@ -83,11 +194,36 @@ pub enum GamePhase {
     TransmissionPhase,
 }
 
+impl GamePhase {
+    /// The phase that follows this one
+    pub fn next(self) -> GamePhase {
+        match self {
+            GamePhase::MutationPhase => GamePhase::InterstitialPhase,
+            GamePhase::InterstitialPhase => GamePhase::TransmissionPhase,
+            GamePhase::TransmissionPhase => GamePhase::MutationPhase,
+        }
+    }
+}
+
+/// Moves submitted in a frame are applied before that frame advances the phase
+#[derive(SystemLabel, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum GameRunnerSystem {
+    ProcessMoves,
+    RunGame,
+}
+
 /// This plugin is responsible for updating the game state
 pub struct MacroPhageGamerunnerPlugin {}
 pub struct GameRunnerRes {
     pub run_game: bool,
     pub game_phase: GamePhase,
+    /// Phase drivers stop advancing the game while paused, it can still be stepped
+    pub paused: bool,
+    /// Multiplier applied to real time phase durations
+    pub speed: f32,
+    /// A transmission phase entered while paused, run one sub phase per
+    /// [`AdvanceSubPhaseEvent`] until it resolves
+    pub transmission: Option<Transmission>,
 }
 
 impl Default for GameRunnerRes {
@@ -95,6 +231,9 @@ impl Default for GameRunnerRes {
         GameRunnerRes {
             run_game: false,
             game_phase: GamePhase::MutationPhase,
+            paused: false,
+            speed: 1.,
+            transmission: None,
         }
     }
 }
@@ -103,10 +242,44 @@ impl Plugin for MacroPhageGamerunnerPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system(init_game);
         app.add_event::<AdvanceGamePhaseEvent>();
+        app.add_event::<AdvanceSubPhaseEvent>();
         app.add_event::<TransmissionEvents>();
         app.add_event::<GameRunnerEvent>();
+        app.add_event::<GameMoveEvent>();
+        app.add_event::<GameMoveRejected>();
+        app.add_event::<GameMoveAccepted>();
         app.insert_resource(GameRunnerRes::default());
-        app.add_system(run_game.run_if(should_run_game));
+        app.init_resource::<PendingOrders>();
+        app.init_resource::<ChecksumHistoryRes>();
+        app.add_event::<PeerChecksumEvent>();
+        app.add_event::<DesyncDetected>();
+        app.add_system(check_peer_checksums.after(GameRunnerSystem::RunGame));
+        app.add_system(
+            process_game_moves
+                .run_if(should_run_game)
+                .label(GameRunnerSystem::ProcessMoves),
+        );
+        app.add_system(
+            run_game
+                .run_if(should_run_game)
+                .label(GameRunnerSystem::RunGame)
+                .after(GameRunnerSystem::ProcessMoves),
+        );
+        app.add_event::<PlayerMutationEvent>();
+        app.add_event::<MutationFailed>();
+        app.add_system(
+            process_map_mutations
+                .run_if(should_run_game)
+                .label(GameRunnerSystem::ProcessMoves),
+        );
+        app.init_resource::<LocalPlayerRes>();
+        app.init_resource::<LevelManagerRes>();
+        app.init_resource::<FogOfWarRes>();
+        app.add_system(update_fog_of_war.after(GameRunnerSystem::RunGame));
+        app.add_system(begin_match.before(GameRunnerSystem::ProcessMoves));
+        app.add_plugin(PhaseDriverPlugin);
+        app.add_plugin(RunnerControlPlugin);
+        app.add_plugin(ReplayPlugin);
     }
 }
 
@@ -118,16 +291,251 @@ fn should_run_game(runner: Res<GameRunnerRes>) -> bool {
     runner.run_game
 }
 
+fn process_game_moves(
+    mut move_events: EventReader<GameMoveEvent>,
+    mut rejections: EventWriter<GameMoveRejected>,
+    mut accepted: EventWriter<GameMoveAccepted>,
+    runner: Res<GameRunnerRes>,
+    mut pending_orders: ResMut<PendingOrders>,
+    mut maps: Query<&mut GameState>,
+) {
+    let mut map = match maps.get_single_mut() {
+        Ok(map) => map,
+        Err(_) => return,
+    };
+
+    for ev in move_events.iter() {
+        let result = submit_move(
+            &mut map,
+            &mut pending_orders,
+            runner.game_phase,
+            ev.force,
+            &ev.game_move,
+        );
+
+        match result {
+            Ok(()) => accepted.send(GameMoveAccepted {
+                force: ev.force,
+                game_move: ev.game_move.clone(),
+            }),
+            Err(reason) => {
+                debug!("Rejected move {:?}: {:?}", ev.game_move, reason);
+                rejections.send(GameMoveRejected {
+                    force: ev.force,
+                    game_move: ev.game_move.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn run_game(
     mut phase_events: EventReader<AdvanceGamePhaseEvent>,
+    mut sub_phase_events: EventReader<AdvanceSubPhaseEvent>,
     mut runner: ResMut<GameRunnerRes>,
     mut runner_events: EventWriter<GameRunnerEvent>,
+    mut transmission_events: EventWriter<TransmissionEvents>,
+    mut pending_orders: ResMut<PendingOrders>,
+    mut checksums: ResMut<ChecksumHistoryRes>,
+    mut maps: Query<&mut GameState>,
 ) {
+    let mut map = maps.get_single_mut().ok();
+
     if let Some(_adv_phase) = phase_events.iter().last() {
-        runner.game_phase = match runner.game_phase {
-            GamePhase::MutationPhase => GamePhase::InterstitialPhase,
-            GamePhase::InterstitialPhase => GamePhase::TransmissionPhase,
-            GamePhase::TransmissionPhase => GamePhase::MutationPhase,
+        sub_phase_events.clear();
+
+        //A stepped transmission has to resolve before the game moves on
+        if let (Some(transmission), Some(map)) = (runner.transmission.take(), map.as_mut()) {
+            resolve(
+                transmission,
+                map,
+                &mut runner,
+                &mut runner_events,
+                &mut transmission_events,
+                &mut pending_orders,
+                &mut checksums,
+            );
+        }
+
+        runner_events.send(GameRunnerEvent::PhaseExited {
+            phase: runner.game_phase,
+        });
+        runner.game_phase = runner.game_phase.next();
+        runner_events.send(GameRunnerEvent::PhaseEntered {
+            phase: runner.game_phase,
+        });
+
+        if runner.game_phase == GamePhase::TransmissionPhase {
+            if let Some(map) = map.as_mut() {
+                let transmission = Transmission::begin(map, &pending_orders);
+                if runner.paused {
+                    runner.transmission = Some(transmission);
+                } else {
+                    resolve(
+                        transmission,
+                        map,
+                        &mut runner,
+                        &mut runner_events,
+                        &mut transmission_events,
+                        &mut pending_orders,
+                        &mut checksums,
+                    );
+                }
+            } else {
+                warn!("Entered the transmission phase without exactly one map loaded");
+                *pending_orders = PendingOrders::default();
+            }
+        }
+    } else if sub_phase_events.iter().last().is_some() {
+        let (mut transmission, mut map) = match (runner.transmission.take(), map) {
+            (Some(transmission), Some(map)) => (transmission, map),
+            _ => return,
+        };
+
+        transmission_events.send_batch(transmission.step().into_iter());
+        if transmission.next_sub_phase().is_some() {
+            *map = transmission.state().clone();
+            runner.transmission = Some(transmission);
+        } else {
+            resolve(
+                transmission,
+                &mut map,
+                &mut runner,
+                &mut runner_events,
+                &mut transmission_events,
+                &mut pending_orders,
+                &mut checksums,
+            );
+        }
+    }
+}
+
+/// Runs whatever sub phases of `transmission` are left and applies the result to `map`
+fn resolve(
+    mut transmission: Transmission,
+    map: &mut GameState,
+    runner: &mut GameRunnerRes,
+    runner_events: &mut EventWriter<GameRunnerEvent>,
+    transmission_events: &mut EventWriter<TransmissionEvents>,
+    pending_orders: &mut PendingOrders,
+    checksums: &mut ChecksumHistoryRes,
+) {
+    while transmission.next_sub_phase().is_some() {
+        transmission_events.send_batch(transmission.step().into_iter());
+    }
+    let (state, _) = transmission.finish();
+    *map = state;
+    *pending_orders = PendingOrders::default();
+    checksums.record(StateChecksum::of(map));
+    runner_events.send(GameRunnerEvent::TransmissionResolved);
+
+    if let Some(victor) = victor(map) {
+        info!("Game completed, {:?} is victorious", victor);
+        runner.run_game = false;
+        runner_events.send(GameRunnerEvent::GameCompleted { victor });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::Events;
+
+    use crate::game::{Cell, Mutation, NodeTenant, Occupant, Replicator, Vector};
+
+    use super::*;
+
+    #[test]
+    pub fn runner_plays_under_minimal_plugins() {
+        let mut map = GameState::new("Pair".to_string(), 1);
+        let from = map.create_node(Force(0), Vec3::ZERO);
+        let to = map.create_node(Force(0), Vec3::X);
+        map.add_vector(Vector::new(from, to)).unwrap();
+        map.nodes.get_mut(&from).unwrap().tenant = NodeTenant::Cell {
+            cell: Cell {
+                occupant: Some(Occupant(Force(0), PhageType::UV)),
+            },
+        };
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(MacroPhageGamerunnerPlugin {});
+        app.world.spawn().insert(map);
+        app.world.resource_mut::<GameRunnerRes>().run_game = true;
+
+        app.world.send_event(GameMoveEvent {
+            force: Force(0),
+            game_move: GameMove::MovePhage { from, to },
+        });
+        for _ in 0..2 {
+            app.world.send_event(AdvanceGamePhaseEvent {});
+            app.update();
+        }
+
+        let state = app.world.query::<&GameState>().single(&app.world);
+        assert_eq!(state.transmission_phases, 1);
+        assert!(state.nodes[&to].occupant().is_some());
+        assert_eq!(
+            app.world.resource::<GameRunnerRes>().game_phase,
+            GamePhase::TransmissionPhase
+        );
+    }
+
+    #[test]
+    pub fn mutating_an_enemy_tile_fails() {
+        let mut map = GameState::new("Enemy".to_string(), 2);
+        let replicator = map.create_node(Force(1), Vec3::ZERO);
+        map.nodes.get_mut(&replicator).unwrap().tenant = NodeTenant::Replicator {
+            replicator: Replicator {
+                output: PhageType::UV,
+                speed: 2,
+                phases_waited: 0,
+            },
         };
+        map.qubits.insert(Force(0), 100);
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(MacroPhageGamerunnerPlugin {});
+        app.world.spawn().insert(map);
+        app.world.resource_mut::<GameRunnerRes>().run_game = true;
+
+        let mutation = Mutation::ChangeReplicatorType {
+            replicator,
+            new_type: PhageType::Sonic,
+            cost: 10,
+        };
+        app.world.send_event(PlayerMutationEvent {
+            mutation: mutation.clone(),
+            force: Force(0),
+        });
+        app.update();
+
+        let events = app.world.resource::<Events<MutationFailed>>();
+        let mut reader = events.get_reader();
+        let failures: Vec<&MutationFailed> = reader.iter(events).collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].mutation, mutation);
+        assert_eq!(failures[0].force, Force(0));
+        assert_eq!(
+            failures[0].reason,
+            PlayerActionError::NotControlledBy {
+                node: replicator,
+                force: Force(0),
+            }
+        );
+
+        let state = app.world.query::<&GameState>().single(&app.world);
+        assert_eq!(state.qubits(Force(0)), 100);
+        assert!(matches!(
+            state.nodes[&replicator].tenant,
+            NodeTenant::Replicator {
+                replicator: Replicator {
+                    output: PhageType::UV,
+                    ..
+                }
+            }
+        ));
     }
 }
@@ -1,89 +1,355 @@
-use bevy::prelude::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
+#[cfg(feature = "client")]
 use crate::util::modelloading::NodeTenentAssets;
-
-use super::{GameState, LevelManagerRes, Mutation, MutationFailed, PlayerMutationEvent};
+#[cfg(feature = "client")]
+use bevy::asset::LoadState;
+
+use super::{
+    gamerunner::{GameMoveAccepted, GameRunnerRes},
+    mapvalidation::MapDiagnosticsRes,
+    validation::validate_mutation,
+    Force, GameMove, GameState, MutationFailed, Nexus, PlayerMutationEvent,
+};
+#[cfg(feature = "client")]
+use super::{
+    mapvalidation::{validate_map, MapDiagnostic},
+    LevelManagerRes,
+};
+
+/// Version written to new maps. Bump it and append a migration whenever a change to
+/// `GameState` would stop older map files from loading as they are
+pub const MAP_FORMAT_VERSION: u32 = 3;
+
+/// `MIGRATIONS[n]` upgrades a map from format version `n` to `n + 1`
+const MIGRATIONS: [fn(&mut Value); MAP_FORMAT_VERSION as usize] = [
+    migrate_unversioned,
+    migrate_v1_metadata,
+    migrate_v2_spawn_slots,
+];
 
 pub fn process_map_mutations(
     mut mutation_events: EventReader<PlayerMutationEvent>,
     mut map_query: Query<(&mut GameState, Entity)>,
+    runner: Res<GameRunnerRes>,
     mut mutation_failure_ev: EventWriter<MutationFailed>,
+    mut accepted: EventWriter<GameMoveAccepted>,
 ) {
-    let (mut map, ent) = map_query.single_mut();
+    let (mut map, _) = match map_query.get_single_mut() {
+        Ok(map) => map,
+        Err(_) => return,
+    };
 
     for mutation_ev in mutation_events.iter() {
-        match &mutation_ev.mutation {
-            //Recombinators Trigger at the beginning of the next interval
-            Mutation::TriggerRecombinator { target, cost } => {}
-
-            //Vectors are removed at time of mutation
-            Mutation::RemoveVector { relation, cost } => {
-                if let Ok(()) = map.remove_vector(*relation) {
-                } else {
-                    mutation_failure_ev.send(MutationFailed {
-                        mutation: mutation_ev.mutation.clone(),
-                    })
-                }
+        let result = validate_mutation(
+            &map,
+            mutation_ev.force,
+            &mutation_ev.mutation,
+            runner.game_phase,
+        )
+        .and_then(|_| map.apply_mutation(mutation_ev.force, &mutation_ev.mutation));
+
+        match result {
+            Ok(()) => accepted.send(GameMoveAccepted {
+                force: mutation_ev.force,
+                game_move: GameMove::Mutate {
+                    mutation: mutation_ev.mutation.clone(),
+                },
+            }),
+            Err(reason) => {
+                debug!("Rejected mutation: {:?}", reason);
+                mutation_failure_ev.send(MutationFailed {
+                    mutation: mutation_ev.mutation.clone(),
+                    force: mutation_ev.force,
+                    reason,
+                })
             }
+        }
+    }
+}
+
+/// Reads a map from `assets/maps`, upgrading it if it was written by an older version
+pub fn load_map(file_name: &str) -> io::Result<GameState> {
+    let file = File::open(format!("assets/maps/{}", file_name))?;
+    read_map(BufReader::new(file))
+}
 
-            //Vectors are added at time of mutation
-            Mutation::AddVector { relation, cost } => {
-                if let Ok(()) = map.add_vector(*relation) {
-                } else {
-                    mutation_failure_ev.send(MutationFailed {
-                        mutation: mutation_ev.mutation.clone(),
-                    })
+/// Parses a map of any supported format version
+pub fn read_map(reader: impl Read) -> io::Result<GameState> {
+    let json = migrate_map(serde_json::from_reader(reader)?)?;
+    Ok(serde_json::from_value(json)?)
+}
+
+/// Runs every migration between the map's format version and [`MAP_FORMAT_VERSION`]
+pub fn migrate_map(mut json: Value) -> io::Result<Value> {
+    let version = json
+        .get("format_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as usize;
+
+    if version > MAP_FORMAT_VERSION as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "map format version {} is newer than the supported version {}",
+                version, MAP_FORMAT_VERSION
+            ),
+        ));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        debug!("Migrating map from format version {}", from);
+        migration(&mut json);
+        json["format_version"] = json!(from + 1);
+    }
+
+    Ok(json)
+}
+
+/// Version 2 maps had no spawn slots, every nexus was one. Forces without a colour of
+/// their own were neutral only because they were drawn white
+fn migrate_v2_spawn_slots(json: &mut Value) {
+    const COLOURED_FORCES: u64 = 6;
+    let neutral = json!(Force::NEUTRAL.0);
+    let mut slots = Vec::default();
+
+    if let Some(nodes) = json.get_mut("nodes").and_then(Value::as_object_mut) {
+        let mut nodes: Vec<&mut Value> = nodes.values_mut().collect();
+        nodes.sort_by_key(|node| node.get("id").and_then(Value::as_u64));
+
+        for node in nodes {
+            let owned_by_player = |force: Option<&Value>| {
+                force
+                    .and_then(Value::as_u64)
+                    .is_some_and(|force| force < COLOURED_FORCES)
+            };
+            if !owned_by_player(node.get("force")) {
+                node["force"] = neutral.clone();
+            } else if node.pointer("/tenant/Nexus").is_some() {
+                slots.push(json!({
+                    "force": node["force"],
+                    "nexus": node["id"],
+                    "occupants": [],
+                }));
+            }
+
+            //Occupants are stored as [force, phage]
+            for occupant in [
+                "/tenant/Cell/cell/occupant",
+                "/tenant/Recombinator/recombinator/occupant",
+            ] {
+                if let Some(force) = node.pointer_mut(&format!("{}/0", occupant)) {
+                    if !owned_by_player(Some(force)) {
+                        *force = neutral.clone();
+                    }
                 }
             }
+        }
+    }
+
+    if let Some(map) = json.as_object_mut() {
+        map.entry("spawn_slots").or_insert(Value::Array(slots));
+    }
+}
+
+/// Version 1 maps had no metadata, they are meant for exactly their number of players
+fn migrate_v1_metadata(json: &mut Value) {
+    let num_players = json.get("num_players").cloned().unwrap_or(json!(0));
+    if let Some(map) = json.as_object_mut() {
+        map.entry("metadata").or_insert(json!({
+            "min_players": num_players,
+            "max_players": num_players,
+        }));
+    }
+}
+
+/// Maps written before versioning: nexuses had no health, replicators no wait counter
+/// and vectors could be stored in either orientation
+fn migrate_unversioned(json: &mut Value) {
+    if let Some(nodes) = json.get_mut("nodes").and_then(Value::as_object_mut) {
+        for node in nodes.values_mut() {
+            if let Some(nexus) = node
+                .pointer_mut("/tenant/Nexus/nexus")
+                .and_then(Value::as_object_mut)
+            {
+                nexus
+                    .entry("health")
+                    .or_insert(json!(Nexus::default().health));
+            }
+            if let Some(replicator) = node
+                .pointer_mut("/tenant/Replicator/replicator")
+                .and_then(Value::as_object_mut)
+            {
+                replicator.entry("phases_waited").or_insert(json!(0));
+            }
+        }
+    }
 
-            //Replicator Output is changed at time of mutation and counter is reset
-            Mutation::ChangeReplicatorType {
-                replicator,
-                new_type,
-                cost,
-            } => {}
+    if let Some(vectors) = json.get_mut("vectors").and_then(Value::as_array_mut) {
+        for vector in vectors.iter_mut() {
+            if let Some(ends) = vector.as_array_mut() {
+                ends.sort_by_key(|end| end.as_u64());
+            }
         }
     }
 }
 
+/// Why the [`MapLoader`] failed to read a map, keyed by asset path. The asset server only
+/// logs load errors, this keeps them around for the map diagnostics
+#[derive(Clone, Default)]
+pub struct MapLoadErrors(Arc<Mutex<HashMap<PathBuf, String>>>);
+
+impl MapLoadErrors {
+    pub fn get(&self, path: &Path) -> Option<String> {
+        self.0.lock().unwrap().get(path).cloned()
+    }
+
+    fn set(&self, path: &Path, error: Option<String>) {
+        let mut errors = self.0.lock().unwrap();
+        match error {
+            Some(error) => errors.insert(path.to_path_buf(), error),
+            None => errors.remove(path),
+        };
+    }
+}
+
+/// Reads `GameState` maps through the asset server, so they load in the background and
+/// hot reload when the file changes on disk
+pub struct MapLoader {
+    errors: MapLoadErrors,
+}
+
+impl AssetLoader for MapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let map = read_map(bytes);
+            self.errors.set(
+                load_context.path(),
+                map.as_ref().err().map(ToString::to_string),
+            );
+            load_context.set_default_asset(LoadedAsset::new(map?));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
+pub struct MapAssetPlugin;
+
+impl Plugin for MapAssetPlugin {
+    fn build(&self, app: &mut App) {
+        let errors = MapLoadErrors::default();
+        app.add_asset::<GameState>()
+            .add_asset_loader(MapLoader {
+                errors: errors.clone(),
+            })
+            .insert_resource(errors)
+            .init_resource::<MapDiagnosticsRes>();
+    }
+}
+
+/// The map asset selected in the level manager and whether it has been spawned yet
+#[cfg(feature = "client")]
+#[derive(Default)]
+pub struct SelectedMap {
+    handle: Option<Handle<GameState>>,
+    spawned: bool,
+}
+
+#[cfg(feature = "client")]
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_map(
     mut commands: Commands,
-    _assets: Res<AssetServer>,
+    asset_server: Res<AssetServer>,
+    map_assets: Res<Assets<GameState>>,
+    mut map_events: EventReader<AssetEvent<GameState>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     //Option just so it doesn't crash while things load
     node_models: Option<Res<NodeTenentAssets>>,
     level_manager: Res<LevelManagerRes>,
-    mut maps: Query<(&GameState, Entity)>,
+    runner: Res<GameRunnerRes>,
+    mut diagnostics: ResMut<MapDiagnosticsRes>,
+    load_errors: Res<MapLoadErrors>,
+    maps: Query<Entity, With<GameState>>,
+    mut selected: Local<SelectedMap>,
 ) {
-    if !level_manager.is_changed() {
-        return;
+    let mut respawn = level_manager.is_changed();
+    if respawn {
+        *selected = SelectedMap {
+            handle: level_manager
+                .current_level
+                .as_ref()
+                .map(|name| asset_server.load(&format!("maps/{}", name))),
+            spawned: false,
+        };
     }
 
-    maps.for_each_mut(|thing| {
-        commands.entity(thing.1).despawn_recursive();
-    });
-
-    if level_manager.current_level == None {
-        return;
+    //Edits to the map file only replace the map while no match is being played on it
+    for event in map_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if selected.handle.as_ref() == Some(handle) && !runner.run_game {
+                info!("Reloading modified map {:?}", level_manager.current_level);
+                selected.spawned = false;
+                respawn = true;
+            }
+        }
     }
 
-    let path = format!(
-        "assets/maps/{}",
-        level_manager.current_level.as_ref().unwrap()
-    );
-    debug!(
-        "Loading level {}",
-        level_manager.current_level.as_ref().unwrap()
-    );
-
-    let file = File::open(path).unwrap();
-
-    let reader = BufReader::new(file);
+    if respawn {
+        maps.for_each(|entity| commands.entity(entity).despawn_recursive());
+        *diagnostics = MapDiagnosticsRes::default();
+    }
 
-    let map: GameState = serde_json::from_reader(reader).unwrap();
+    let handle = match &selected.handle {
+        Some(handle) if !selected.spawned => handle.clone(),
+        _ => return,
+    };
+    let map_name = level_manager.current_level.clone().unwrap_or_default();
+
+    let map = match (
+        map_assets.get(&handle),
+        asset_server.get_load_state(&handle),
+    ) {
+        (Some(map), _) => Ok(map.clone()),
+        (None, LoadState::Failed) => Err(load_errors
+            .get(Path::new(&format!("maps/{}", map_name)))
+            .unwrap_or_else(|| "unknown error".to_string())),
+        _ => return,
+    };
+
+    selected.spawned = true;
+    diagnostics.map = Some(map_name.clone());
+    diagnostics.diagnostics = match &map {
+        Ok(map) => validate_map(map),
+        Err(err) => vec![MapDiagnostic::Unreadable(err.clone())],
+    };
+
+    let map = match map {
+        Ok(map) if !diagnostics.has_errors() => map,
+        _ => {
+            warn!("Not spawning {}: {:?}", map_name, diagnostics.diagnostics);
+            return;
+        }
+    };
+    debug!("Spawning level {}", map_name);
 
     let map_ent = commands
         .spawn_bundle(SpatialBundle::default())
@@ -102,8 +368,10 @@ pub fn spawn_map(
 
     let mut vector_ents = Vec::default();
     for vector in map.vectors.iter() {
-        let node_0 = map.nodes.get(&vector.0).unwrap();
-        let node_1 = map.nodes.get(&vector.1).unwrap();
+        let (node_0, node_1) = match (map.nodes.get(&vector.0), map.nodes.get(&vector.1)) {
+            (Some(node_0), Some(node_1)) => (node_0, node_1),
+            _ => continue,
+        };
 
         let pos = (node_0.position + node_1.position) / 2.0;
         let mut transform = Transform::from_translation(pos).looking_at(node_1.position, Vec3::Y);
@@ -138,29 +406,19 @@ pub fn spawn_map(
 
 #[cfg(test)]
 mod tests {
-    use bevy::utils::HashMap;
-
-    use crate::game::{Force, Generator, Nexus, NodeId, NodeTenant, PhageType, Replicator, Vector};
+    use crate::game::{
+        gamerunner::{GamePhase, TransmissionEvents},
+        mapinfo::MapMetadata,
+        transmission::{transmit, PendingOrders},
+        Force, Generator, Mutation, Nexus, NodeId, NodeTenant, Occupant, PhageType,
+        PlayerActionError, Replicator, SpawnSlot, Vector,
+    };
 
     use super::*;
 
-    #[test]
-    pub fn create_test_map() {
-        use std::fs::File;
-        use std::io::prelude::*;
-
-        let nodes = HashMap::new();
-        let vectors = vec![];
-        let num_players = 5;
-
-        let name: String = "Hello Map".to_string();
-        let mut map = GameState {
-            nodes,
-            next_free_id: NodeId(0),
-            vectors,
-            name,
-            num_players,
-        };
+    /// The map written to `assets/maps/test_map.json`
+    fn test_map() -> GameState {
+        let mut map = GameState::new("Hello Map".to_string(), 5);
 
         let node_1 = map.create_node(Force(0), Vec3::ZERO);
         let node_2 = map.create_node(Force(1), Vec3::new(1., 0., 3.));
@@ -176,15 +434,22 @@ mod tests {
             replicator: Replicator {
                 output: PhageType::Electro,
                 speed: 3,
+                phases_waited: 0,
             },
         };
 
         let nexus_node = map.nodes.get_mut(&node_6).expect("fuck 2");
-        nexus_node.tenant = NodeTenant::Nexus { nexus: Nexus {} };
+        nexus_node.tenant = NodeTenant::Nexus {
+            nexus: Nexus::default(),
+        };
 
         let generator_node = map.nodes.get_mut(&node_7).expect("fuck 3");
         generator_node.tenant = NodeTenant::Generator {
-            generator: Generator { amt: 50, speed: 1 },
+            generator: Generator {
+                amt: 50,
+                speed: 1,
+                occupant: None,
+            },
         };
 
         map.add_vector(Vector::new(node_4, node_1)).unwrap();
@@ -203,12 +468,204 @@ mod tests {
         map.add_vector(Vector::new(node_7, node_5)).unwrap();
         assert!(map.vectors.len() == 6);
 
+        map
+    }
+
+    #[test]
+    pub fn create_test_map() {
+        use std::fs::File;
+        use std::io::prelude::*;
+
+        let map = test_map();
         let map_json = serde_json::to_string(&map).unwrap();
-        let mut input = File::create("assets/maps/test_map.json").unwrap();
+        //Written outside the repo so running the tests never touches the checked in asset
+        let path = std::env::temp_dir().join("macrophage_test_map.json");
+        let mut input = File::create(&path).unwrap();
         // https://doc.rust-lang.org/std/fs/struct.File.html
         input.write_all(map_json.as_bytes()).unwrap();
 
-        println!("File Created Luv u -- ur pc");
+        assert_eq!(read_map(File::open(&path).unwrap()).unwrap(), map);
+        assert_eq!(load_map("test_map.json").unwrap(), map);
+    }
+
+    #[test]
+    pub fn mutations_cost_qubits() {
+        let mut map = GameState::new("Costs".to_string(), 2);
+        let node_1 = map.create_node(Force(0), Vec3::ZERO);
+        let node_2 = map.create_node(Force(0), Vec3::X);
+        let mutation = Mutation::AddVector {
+            relation: Vector::new(node_1, node_2),
+            cost: 10,
+        };
+
+        assert!(matches!(
+            map.apply_mutation(Force(0), &mutation),
+            Err(PlayerActionError::InsufficientQubits {
+                cost: 10,
+                available: 0
+            })
+        ));
+        assert!(map.vectors.is_empty());
+
+        map.grant_qubits(Force(0), 15);
+        map.apply_mutation(Force(0), &mutation).unwrap();
+        assert_eq!(map.qubits(Force(0)), 5);
+        assert!(map.vector_exists(Vector::new(node_1, node_2)));
+    }
+
+    #[test]
+    pub fn test_map_replicator_spawns_phage() {
+        let map = test_map();
+        let replicator = NodeId(4);
+        let mut state = map.clone();
+
+        for _ in 0..2 {
+            state = transmit(&state, &PendingOrders::default()).0;
+            assert!(state.nodes[&NodeId(0)].occupant().is_none());
+        }
+
+        let (state, events) = transmit(&state, &PendingOrders::default());
+        assert!(events.contains(&TransmissionEvents::PhageReplicated {
+            replicator,
+            cell: NodeId(0),
+            phage: (Force(4), PhageType::Electro),
+        }));
+        assert_eq!(
+            state.nodes[&NodeId(0)].occupant(),
+            Some(&Occupant(Force(4), PhageType::Electro))
+        );
+
+        //The only free neighbor is now taken, so the replicator waits for space
+        let (state, events) = transmit(&state, &PendingOrders::default());
+        let (_, events_2) = transmit(&state, &PendingOrders::default());
+        let (_, events_3) = transmit(&state, &PendingOrders::default());
+        for events in [events, events_2, events_3] {
+            assert!(!events
+                .iter()
+                .any(|event| matches!(event, TransmissionEvents::PhageReplicated { .. })));
+        }
+    }
+
+    #[test]
+    pub fn changing_replicator_type_resets_its_counter() {
+        let map = test_map();
+        let (mut state, _) = transmit(&map, &PendingOrders::default());
+        let (state_2, _) = transmit(&state, &PendingOrders::default());
+        assert_eq!(replicator_in(&state_2, NodeId(4)).phases_waited, 2);
+
+        let mutation = Mutation::ChangeReplicatorType {
+            replicator: NodeId(4),
+            new_type: PhageType::Sonic,
+            cost: 0,
+        };
+        state.apply_mutation(Force(4), &mutation).unwrap();
+
+        let replicator = replicator_in(&state, NodeId(4));
+        assert_eq!(replicator.output, PhageType::Sonic);
+        assert_eq!(replicator.phases_waited, 0);
+
+        let nexus = Mutation::ChangeReplicatorType {
+            replicator: NodeId(5),
+            new_type: PhageType::Sonic,
+            cost: 0,
+        };
+        assert!(matches!(
+            state.apply_mutation(Force(5), &nexus),
+            Err(PlayerActionError::NotAReplicator(NodeId(5)))
+        ));
+        assert!(matches!(
+            validate_mutation(&state, Force(0), &mutation, GamePhase::MutationPhase),
+            Err(PlayerActionError::NotControlledBy {
+                node: NodeId(4),
+                force: Force(0)
+            })
+        ));
+    }
+
+    #[test]
+    pub fn maps_load_from_every_format_version() {
+        let unversioned = read_map(&include_bytes!("fixtures/map_v0.json")[..]).unwrap();
+        let v1 = read_map(&include_bytes!("fixtures/map_v1.json")[..]).unwrap();
+
+        for map in [&unversioned, &v1] {
+            assert_eq!(map.format_version, MAP_FORMAT_VERSION);
+            assert!(matches!(
+                map.nodes[&NodeId(5)].tenant,
+                NodeTenant::Nexus {
+                    nexus: Nexus { health: 5 }
+                }
+            ));
+            assert_eq!(replicator_in(map, NodeId(4)).phases_waited, 0);
+            assert!(map.vector_exists(Vector::new(NodeId(0), NodeId(3))));
+            assert_eq!(map.metadata, MapMetadata::for_players(5));
+        }
+        assert_eq!(unversioned.vectors, v1.vectors);
+
+        let v2 = read_map(&include_bytes!("fixtures/map_v2.json")[..]).unwrap();
+        assert_eq!(v2.vectors, v1.vectors);
+        assert_eq!(v2.metadata.author, "MacroPhage");
+        assert!(v2.metadata.supports(2) && !v2.metadata.supports(6));
+
+        for map in [&unversioned, &v1, &v2] {
+            assert_eq!(map.nodes[&NodeId(6)].force, Force::NEUTRAL);
+            assert_eq!(
+                map.spawn_slots,
+                vec![SpawnSlot {
+                    force: Force(5),
+                    nexus: NodeId(5),
+                    occupants: Vec::default(),
+                }]
+            );
+        }
+
+        let v3 = read_map(&include_bytes!("fixtures/map_v3.json")[..]).unwrap();
+        assert_eq!(v3.metadata, v2.metadata);
+        assert_eq!(v3.nodes[&NodeId(6)].force, Force::NEUTRAL);
+        assert_eq!(
+            v3.spawn_slots[0].occupants,
+            vec![(NodeId(0), PhageType::UV)]
+        );
+
+        //Maps from before fog of war keep playing without it
+        for map in [&unversioned, &v1, &v2, &v3] {
+            assert!(!map.fog.enabled);
+        }
+
+        let future = json!({ "format_version": MAP_FORMAT_VERSION + 1 });
+        assert!(migrate_map(future).is_err());
+    }
+
+    #[test]
+    pub fn vectors_keep_an_adjacency_index() {
+        let mut map = test_map();
+        assert_eq!(
+            map.get_all_neighbors(NodeId(0)),
+            vec![NodeId(1), NodeId(2), NodeId(3), NodeId(4)]
+        );
+
+        map.remove_vector(Vector(NodeId(4), NodeId(0))).unwrap();
+        assert_eq!(
+            map.get_all_neighbors(NodeId(0)),
+            vec![NodeId(1), NodeId(2), NodeId(3)]
+        );
+        assert_eq!(map.get_all_neighbors(NodeId(4)), vec![NodeId(5), NodeId(6)]);
+
+        //Maps written with a plain vector list, in any orientation, still load
+        let mut json = serde_json::to_value(&map).unwrap();
+        json["vectors"] = serde_json::json!([[5, 4], [0, 1], [4, 6], [0, 3], [2, 0], [1, 0]]);
+        let loaded: GameState = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded, map);
+        assert_eq!(
+            loaded.get_all_neighbors(NodeId(4)),
+            vec![NodeId(5), NodeId(6)]
+        );
+    }
+
+    fn replicator_in(state: &GameState, id: NodeId) -> &Replicator {
+        match &state.nodes[&id].tenant {
+            NodeTenant::Replicator { replicator } => replicator,
+            _ => panic!("{:?} is not a replicator", id),
+        }
     }
 }
 
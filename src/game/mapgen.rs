@@ -0,0 +1,378 @@
+use std::f32::consts::TAU;
+use std::fmt;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{editor::TenantKind, Force, GameState, NodeId, PhageType, Vector};
+
+/// Closest two generated nodes may be to each other
+const MIN_NODE_SPACING: f32 = 2.5;
+
+/// Attempts at finding a free spot for a node before giving up on spacing
+const PLACEMENT_ATTEMPTS: u32 = 64;
+
+/// How every player's share of the map relates to the others
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Symmetry {
+    /// Each player's sector is the first one rotated around the centre of the map
+    #[default]
+    Rotational,
+    /// Neighbouring sectors are reflections of each other, needs an even number of players
+    Mirror,
+}
+
+/// How many of each special tenant every player's sector gets, the rest are cells
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TenantMix {
+    pub replicators: u32,
+    pub generators: u32,
+    pub recombinators: u32,
+}
+
+impl Default for TenantMix {
+    fn default() -> Self {
+        TenantMix {
+            replicators: 1,
+            generators: 1,
+            recombinators: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct MapGenSettings {
+    pub seed: u64,
+    pub players: u32,
+    /// Total nodes on the map, rounded down to a multiple of `players`
+    pub nodes: u32,
+    pub mix: TenantMix,
+    pub symmetry: Symmetry,
+    /// Distance from the centre of the map to its edge
+    pub radius: f32,
+}
+
+impl Default for MapGenSettings {
+    fn default() -> Self {
+        MapGenSettings {
+            seed: 0,
+            players: 2,
+            nodes: 24,
+            mix: TenantMix::default(),
+            symmetry: Symmetry::default(),
+            radius: 20.,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MapGenError {
+    NoPlayers,
+    /// Every sector needs room for a nexus and the requested tenants
+    TooFewNodes {
+        per_player: u32,
+        needed: u32,
+    },
+    MirrorNeedsEvenPlayers(u32),
+}
+
+impl fmt::Display for MapGenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapGenError::NoPlayers => write!(f, "a map needs at least one player"),
+            MapGenError::TooFewNodes { per_player, needed } => write!(
+                f,
+                "{} nodes per player cannot hold a nexus and the requested tenants, {} are needed",
+                per_player, needed
+            ),
+            MapGenError::MirrorNeedsEvenPlayers(players) => write!(
+                f,
+                "mirror symmetry needs an even number of players, not {}",
+                players
+            ),
+        }
+    }
+}
+
+/// A node of the first sector, in polar coordinates around the centre of the map
+struct SectorNode {
+    radius: f32,
+    angle: f32,
+    kind: TenantKind,
+}
+
+/// Generates a connected map where every player's sector is a copy of the first, so no
+/// position is favoured. The same settings always produce the same map
+pub fn generate_map(settings: &MapGenSettings) -> Result<GameState, MapGenError> {
+    let players = settings.players;
+    if players == 0 {
+        return Err(MapGenError::NoPlayers);
+    }
+    if settings.symmetry == Symmetry::Mirror && players % 2 == 1 {
+        return Err(MapGenError::MirrorNeedsEvenPlayers(players));
+    }
+
+    let mix = &settings.mix;
+    let per_player = settings.nodes / players;
+    let needed = 1 + mix.replicators + mix.generators + mix.recombinators;
+    if per_player < needed {
+        return Err(MapGenError::TooFewNodes { per_player, needed });
+    }
+
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let sector = generate_sector(&mut rng, settings, per_player);
+    let links = connect_sector(&sector);
+
+    let name = format!(
+        "Generated {:?} {}p #{}",
+        settings.symmetry, players, settings.seed
+    );
+    let mut state = GameState::new(name, players);
+    state.metadata.description = format!(
+        "{} nodes generated from seed {}",
+        per_player * players,
+        settings.seed
+    );
+    state.metadata.tags = vec![
+        "generated".to_string(),
+        format!("{:?}", settings.symmetry).to_lowercase(),
+    ];
+    let width = TAU / players as f32;
+
+    //Node j of sector i gets id i * per_player + j
+    let id = |sector: u32, node: usize| NodeId(sector * per_player + node as u32);
+    let near_nexus: Vec<bool> = (0..sector.len())
+        .map(|j| j == 0 || links.contains(&(0, j)))
+        .collect();
+
+    for i in 0..players {
+        let reflected = settings.symmetry == Symmetry::Mirror && i % 2 == 1;
+        for (j, node) in sector.iter().enumerate() {
+            let angle = match reflected {
+                true => -node.angle,
+                false => node.angle,
+            } + width * i as f32;
+            let force = match near_nexus[j] {
+                true => Force(i),
+                false => Force::NEUTRAL,
+            };
+
+            let position = Vec3::new(angle.cos(), 0., angle.sin()) * node.radius;
+            let created = state.create_node(force, position);
+            state.nodes.get_mut(&created).unwrap().tenant = node.kind.tenant(PhageType::UV);
+        }
+
+        for (a, b) in links.iter() {
+            state
+                .add_vector(Vector::new(id(i, *a), id(i, *b)))
+                .expect("sector links are unique");
+        }
+    }
+
+    //Bridge neighbouring sectors through the nodes closest to their shared edge
+    let (low, high) = sector_edges(&sector);
+    if players > 1 {
+        for i in 0..players {
+            let next = (i + 1) % players;
+            let (from, to) = match settings.symmetry {
+                Symmetry::Rotational => (high, low),
+                Symmetry::Mirror if i % 2 == 0 => (high, high),
+                Symmetry::Mirror => (low, low),
+            };
+            let bridge = Vector::new(id(i, from), id(next, to));
+            if !state.vector_exists(bridge) {
+                state
+                    .add_vector(bridge)
+                    .expect("bridges join distinct nodes");
+            }
+        }
+    }
+
+    state.refresh_spawn_slots();
+    Ok(state)
+}
+
+/// Places the nodes of the first sector, the nexus first, inside the wedge of angles
+/// centred on zero that the sector covers
+fn generate_sector(rng: &mut StdRng, settings: &MapGenSettings, count: u32) -> Vec<SectorNode> {
+    let half_width = TAU / settings.players as f32 / 2.;
+    let mix = &settings.mix;
+
+    let mut kinds = vec![TenantKind::Nexus];
+    kinds.extend((0..mix.replicators).map(|_| TenantKind::Replicator));
+    kinds.extend((0..mix.generators).map(|_| TenantKind::Generator));
+    kinds.extend((0..mix.recombinators).map(|_| TenantKind::Recombinator));
+    kinds.resize(count as usize, TenantKind::Cell);
+
+    let mut sector: Vec<SectorNode> = Vec::default();
+    for kind in kinds {
+        let (radius, angle) = match kind {
+            TenantKind::Nexus => (settings.radius * 0.8, 0.),
+            _ => {
+                let mut spot = (0., 0.);
+                for _ in 0..PLACEMENT_ATTEMPTS {
+                    //Keep clear of the sector edges so mirrored sectors do not overlap
+                    spot = (
+                        rng.gen_range(MIN_NODE_SPACING..settings.radius),
+                        rng.gen_range(-half_width * 0.9..half_width * 0.9),
+                    );
+                    if sector.iter().all(|node| {
+                        polar_distance(node.radius, node.angle, spot) >= MIN_NODE_SPACING
+                    }) {
+                        break;
+                    }
+                }
+                spot
+            }
+        };
+        sector.push(SectorNode {
+            radius,
+            angle,
+            kind,
+        });
+    }
+
+    sector
+}
+
+/// Vectors within a sector: a minimum spanning tree so the sector is connected, plus a
+/// shortcut from every node to its nearest neighbour
+fn connect_sector(sector: &[SectorNode]) -> Vec<(usize, usize)> {
+    let distance = |a: usize, b: usize| {
+        polar_distance(
+            sector[a].radius,
+            sector[a].angle,
+            (sector[b].radius, sector[b].angle),
+        )
+    };
+
+    let mut links = Vec::default();
+    let mut in_tree = vec![false; sector.len()];
+    in_tree[0] = true;
+    for _ in 1..sector.len() {
+        let (a, b) = (0..sector.len())
+            .filter(|a| in_tree[*a])
+            .flat_map(|a| {
+                (0..sector.len())
+                    .filter(|b| !in_tree[*b])
+                    .map(move |b| (a, b))
+            })
+            .min_by(|x, y| distance(x.0, x.1).total_cmp(&distance(y.0, y.1)))
+            .unwrap();
+        in_tree[b] = true;
+        links.push((a.min(b), a.max(b)));
+    }
+
+    for a in 0..sector.len() {
+        let nearest = (0..sector.len())
+            .filter(|b| *b != a)
+            .min_by(|x, y| distance(a, *x).total_cmp(&distance(a, *y)));
+        if let Some(b) = nearest {
+            let link = (a.min(b), a.max(b));
+            if !links.contains(&link) {
+                links.push(link);
+            }
+        }
+    }
+
+    links
+}
+
+/// The sector nodes closest to the low and high angle edges of the sector
+fn sector_edges(sector: &[SectorNode]) -> (usize, usize) {
+    let by_angle = |a: &usize, b: &usize| sector[*a].angle.total_cmp(&sector[*b].angle);
+    let low = (0..sector.len()).min_by(by_angle).unwrap();
+    let high = (0..sector.len()).max_by(by_angle).unwrap();
+    (low, high)
+}
+
+fn polar_distance(radius: f32, angle: f32, (other_radius, other_angle): (f32, f32)) -> f32 {
+    let a = Vec2::new(angle.cos(), angle.sin()) * radius;
+    let b = Vec2::new(other_angle.cos(), other_angle.sin()) * other_radius;
+    a.distance(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        mapvalidation::{validate_map, MapDiagnostic},
+        NodeTenant,
+    };
+
+    use super::*;
+
+    fn nexuses(state: &GameState) -> Vec<NodeId> {
+        state
+            .nodes
+            .values()
+            .filter(|node| matches!(node.tenant, NodeTenant::Nexus { .. }))
+            .map(|node| node.id)
+            .collect()
+    }
+
+    #[test]
+    pub fn generated_maps_are_connected_and_fair() {
+        for symmetry in [Symmetry::Rotational, Symmetry::Mirror] {
+            let settings = MapGenSettings {
+                seed: 7,
+                players: 4,
+                nodes: 40,
+                symmetry,
+                ..Default::default()
+            };
+            let state = generate_map(&settings).unwrap();
+            assert_eq!(state, generate_map(&settings).unwrap());
+            assert_eq!(state.nodes.len(), 40);
+            assert!(validate_map(&state).is_empty());
+            assert_eq!(state.spawn_slots.len(), 4);
+
+            //Every nexus sees the same distances to everything else on the map
+            let mut distances: Vec<Vec<u32>> = nexuses(&state)
+                .into_iter()
+                .map(|nexus| {
+                    let mut hops: Vec<u32> = state.hops_from([nexus]).into_values().collect();
+                    hops.sort();
+                    hops
+                })
+                .collect();
+            distances.dedup();
+            assert_eq!(distances.len(), 1);
+        }
+    }
+
+    #[test]
+    pub fn impossible_settings_are_rejected() {
+        let mirror = MapGenSettings {
+            players: 3,
+            symmetry: Symmetry::Mirror,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_map(&mirror),
+            Err(MapGenError::MirrorNeedsEvenPlayers(3))
+        );
+
+        let crowded = MapGenSettings {
+            nodes: 6,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_map(&crowded),
+            Err(MapGenError::TooFewNodes {
+                per_player: 3,
+                needed: 4
+            })
+        );
+
+        let single = generate_map(&MapGenSettings {
+            players: 1,
+            nodes: 5,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!validate_map(&single)
+            .iter()
+            .any(|diagnostic| matches!(diagnostic, MapDiagnostic::UnreachableNode(_))));
+    }
+}
@@ -0,0 +1,158 @@
+#[cfg(feature = "client")]
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client")]
+use super::GameState;
+
+#[cfg(feature = "client")]
+/// Background of generated map previews
+const PREVIEW_BACKGROUND: [u8; 4] = [0, 38, 38, 255];
+#[cfg(feature = "client")]
+const PREVIEW_VECTOR: [u8; 4] = [200, 40, 40, 255];
+#[cfg(feature = "client")]
+/// Radius of a node in preview pixels
+const PREVIEW_NODE_RADIUS: i32 = 3;
+#[cfg(feature = "client")]
+/// Empty pixels kept between the outermost nodes and the edge of a preview
+const PREVIEW_MARGIN: f32 = 8.;
+
+/// Everything the lobby shows about a map before it is played
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct MapMetadata {
+    pub description: String,
+    pub author: String,
+    /// Fewest players the map is meant to be played with
+    pub min_players: u32,
+    /// Most players the map is meant to be played with
+    pub max_players: u32,
+    pub tags: Vec<String>,
+    /// Image under `assets` shown instead of the generated top-down preview
+    pub preview: Option<String>,
+}
+
+impl MapMetadata {
+    /// Metadata for a new map made for exactly `num_players`
+    pub fn for_players(num_players: u32) -> Self {
+        MapMetadata {
+            min_players: num_players,
+            max_players: num_players,
+            ..Default::default()
+        }
+    }
+
+    pub fn supports(&self, players: u32) -> bool {
+        (self.min_players..=self.max_players).contains(&players)
+    }
+}
+
+#[cfg(feature = "client")]
+/// A top-down picture of the map, `size` pixels square, with nodes in the colour of
+/// their force and vectors drawn between them
+pub fn preview_image(state: &GameState, size: u32) -> Image {
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        preview_pixels(state, size),
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+#[cfg(feature = "client")]
+/// The RGBA pixels of [`preview_image`], row by row
+pub fn preview_pixels(state: &GameState, size: u32) -> Vec<u8> {
+    let mut pixels = PREVIEW_BACKGROUND.repeat((size * size) as usize);
+
+    //Fit the ground plane extent of the map into the image, keeping its aspect
+    let (min, max) = state.nodes.values().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), node| {
+            let point = Vec2::new(node.position.x, node.position.z);
+            (min.min(point), max.max(point))
+        },
+    );
+    let extent = (max - min).max_element().max(1.);
+    let scale = (size as f32 - PREVIEW_MARGIN * 2.) / extent;
+    let centre = (min + max) / 2.;
+    let to_pixel = |position: Vec3| {
+        let point = (Vec2::new(position.x, position.z) - centre) * scale + size as f32 / 2.;
+        (point.x.round() as i32, point.y.round() as i32)
+    };
+
+    let mut plot = |x: i32, y: i32, color: [u8; 4]| {
+        if x >= 0 && y >= 0 && x < size as i32 && y < size as i32 {
+            let index = ((y as u32 * size + x as u32) * 4) as usize;
+            pixels[index..index + 4].copy_from_slice(&color);
+        }
+    };
+
+    for vector in state.vectors.iter() {
+        let (start, end) = match (state.nodes.get(&vector.0), state.nodes.get(&vector.1)) {
+            (Some(start), Some(end)) => (to_pixel(start.position), to_pixel(end.position)),
+            _ => continue,
+        };
+        let steps = (end.0 - start.0).abs().max((end.1 - start.1).abs()).max(1);
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            plot(
+                start.0 + ((end.0 - start.0) as f32 * t).round() as i32,
+                start.1 + ((end.1 - start.1) as f32 * t).round() as i32,
+                PREVIEW_VECTOR,
+            );
+        }
+    }
+
+    for node in state.nodes.values() {
+        let (x, y) = to_pixel(node.position);
+        let color = node.force.color().as_rgba_u32().to_le_bytes();
+        for dy in -PREVIEW_NODE_RADIUS..=PREVIEW_NODE_RADIUS {
+            for dx in -PREVIEW_NODE_RADIUS..=PREVIEW_NODE_RADIUS {
+                if dx * dx + dy * dy <= PREVIEW_NODE_RADIUS * PREVIEW_NODE_RADIUS {
+                    plot(x + dx, y + dy, color);
+                }
+            }
+        }
+    }
+
+    pixels
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use crate::game::{Force, Vector};
+
+    use super::*;
+
+    #[test]
+    pub fn previews_show_nodes_and_vectors() {
+        let mut map = GameState::new("Preview".to_string(), 2);
+        let left = map.create_node(Force(0), Vec3::new(-10., 0., 0.));
+        let right = map.create_node(Force(1), Vec3::new(10., 0., 0.));
+        map.add_vector(Vector::new(left, right)).unwrap();
+
+        let size = 64;
+        let pixels = preview_pixels(&map, size);
+        let pixel = |x: u32, y: u32| {
+            let index = ((y * size + x) * 4) as usize;
+            [
+                pixels[index],
+                pixels[index + 1],
+                pixels[index + 2],
+                pixels[index + 3],
+            ]
+        };
+
+        assert_eq!(pixel(8, 32), Force(0).color().as_rgba_u32().to_le_bytes());
+        assert_eq!(pixel(56, 32), Force(1).color().as_rgba_u32().to_le_bytes());
+        assert_eq!(pixel(32, 32), PREVIEW_VECTOR);
+        assert_eq!(pixel(32, 4), PREVIEW_BACKGROUND);
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::{GameState, NodeId, NodeTenant, Vector};
+
+/// A problem found in a map file, errors stop the map from being played
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MapDiagnostic {
+    /// The map file could not be read or parsed at all
+    Unreadable(String),
+    /// A node is stored under a different id than the one it carries
+    MismatchedNodeId { key: NodeId, id: NodeId },
+    /// A vector points at a node that does not exist
+    DanglingVector { vector: Vector, missing: NodeId },
+    /// A vector connects a node to itself
+    IllFormedVector(Vector),
+    /// `next_free_id` would hand out an id that is already taken
+    NextFreeIdInUse {
+        next_free_id: NodeId,
+        highest: NodeId,
+    },
+    /// No path of vectors leads from any nexus to this node
+    UnreachableNode(NodeId),
+    /// The map has a different number of nexuses than players
+    NexusCount { nexuses: usize, num_players: u32 },
+    /// A spawn slot starts on a node that does not exist or is not a nexus
+    BrokenSpawnSlot { slot: usize, nexus: NodeId },
+}
+
+impl MapDiagnostic {
+    /// Errors would crash or corrupt a match, the rest are only worth a map author's attention
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            MapDiagnostic::UnreachableNode(_) | MapDiagnostic::NexusCount { .. }
+        )
+    }
+}
+
+impl fmt::Display for MapDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapDiagnostic::Unreadable(reason) => write!(f, "The map could not be read: {}", reason),
+            MapDiagnostic::MismatchedNodeId { key, id } => {
+                write!(f, "Node {:?} is stored under id {:?}", id, key)
+            }
+            MapDiagnostic::DanglingVector { vector, missing } => write!(
+                f,
+                "Vector {:?} leads to node {:?} which does not exist",
+                vector, missing
+            ),
+            MapDiagnostic::IllFormedVector(vector) => {
+                write!(f, "Vector {:?} connects a node to itself", vector)
+            }
+            MapDiagnostic::NextFreeIdInUse {
+                next_free_id,
+                highest,
+            } => write!(
+                f,
+                "The next free id {:?} is not above the highest node id {:?}",
+                next_free_id, highest
+            ),
+            MapDiagnostic::UnreachableNode(id) => {
+                write!(f, "Node {:?} cannot be reached from any nexus", id)
+            }
+            MapDiagnostic::NexusCount {
+                nexuses,
+                num_players,
+            } => write!(
+                f,
+                "The map has {} nexuses for {} players",
+                nexuses, num_players
+            ),
+            MapDiagnostic::BrokenSpawnSlot { slot, nexus } => write!(
+                f,
+                "Spawn slot {} starts on node {:?} which is not a nexus",
+                slot, nexus
+            ),
+        }
+    }
+}
+
+/// Diagnostics of the map the level manager last tried to load
+#[derive(Default)]
+pub struct MapDiagnosticsRes {
+    pub map: Option<String>,
+    pub diagnostics: Vec<MapDiagnostic>,
+}
+
+impl MapDiagnosticsRes {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(MapDiagnostic::is_error)
+    }
+}
+
+/// Checks a loaded map for problems that would break or unbalance a match.
+/// Duplicate and reversed vectors are already merged when a map is loaded, so
+/// only self loops are left to report as ill formed
+pub fn validate_map(state: &GameState) -> Vec<MapDiagnostic> {
+    let mut diagnostics = Vec::default();
+
+    for (key, node) in state.nodes.iter() {
+        if *key != node.id {
+            diagnostics.push(MapDiagnostic::MismatchedNodeId {
+                key: *key,
+                id: node.id,
+            });
+        }
+    }
+
+    for vector in state.vectors.iter() {
+        if !vector.is_well_formed() {
+            diagnostics.push(MapDiagnostic::IllFormedVector(*vector));
+        }
+
+        for end in [vector.0, vector.1] {
+            if !state.nodes.contains_key(&end) {
+                diagnostics.push(MapDiagnostic::DanglingVector {
+                    vector: *vector,
+                    missing: end,
+                });
+            }
+        }
+    }
+
+    if let Some(highest) = state.nodes.keys().next_back() {
+        if state.next_free_id <= *highest {
+            diagnostics.push(MapDiagnostic::NextFreeIdInUse {
+                next_free_id: state.next_free_id,
+                highest: *highest,
+            });
+        }
+    }
+
+    let nexuses: BTreeSet<NodeId> = state
+        .nodes
+        .values()
+        .filter(|node| matches!(node.tenant, NodeTenant::Nexus { .. }))
+        .map(|node| node.id)
+        .collect();
+
+    if nexuses.len() != state.num_players as usize {
+        diagnostics.push(MapDiagnostic::NexusCount {
+            nexuses: nexuses.len(),
+            num_players: state.num_players,
+        });
+    }
+
+    for (slot, spawn) in state.spawn_slots.iter().enumerate() {
+        if !nexuses.contains(&spawn.nexus) {
+            diagnostics.push(MapDiagnostic::BrokenSpawnSlot {
+                slot,
+                nexus: spawn.nexus,
+            });
+        }
+    }
+
+    if !nexuses.is_empty() {
+        let reachable = state.hops_from(nexuses);
+        for id in state.nodes.keys() {
+            if !reachable.contains_key(id) {
+                diagnostics.push(MapDiagnostic::UnreachableNode(*id));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{Force, Nexus, SpawnSlot};
+
+    use super::*;
+
+    #[test]
+    pub fn broken_maps_are_diagnosed() {
+        let mut map = GameState::new("Broken".to_string(), 2);
+        let nexus = map.create_node(Force(0), Vec3::ZERO);
+        let cell = map.create_node(Force(0), Vec3::X);
+        let island = map.create_node(Force(1), Vec3::Z);
+        map.nodes.get_mut(&nexus).unwrap().tenant = NodeTenant::Nexus {
+            nexus: Nexus::default(),
+        };
+        map.add_vector(Vector::new(nexus, cell)).unwrap();
+        assert_eq!(
+            validate_map(&map),
+            vec![
+                MapDiagnostic::NexusCount {
+                    nexuses: 1,
+                    num_players: 2
+                },
+                MapDiagnostic::UnreachableNode(island),
+            ]
+        );
+
+        map.vectors.insert(Vector(cell, cell));
+        map.vectors.insert(Vector::new(cell, NodeId(7)));
+        map.next_free_id = island;
+        map.spawn_slots.push(SpawnSlot {
+            force: Force(1),
+            nexus: island,
+            occupants: Vec::default(),
+        });
+        let diagnostics = validate_map(&map);
+        assert!(diagnostics.contains(&MapDiagnostic::IllFormedVector(Vector(cell, cell))));
+        assert!(diagnostics.contains(&MapDiagnostic::DanglingVector {
+            vector: Vector::new(cell, NodeId(7)),
+            missing: NodeId(7)
+        }));
+        assert!(diagnostics.contains(&MapDiagnostic::NextFreeIdInUse {
+            next_free_id: island,
+            highest: island
+        }));
+        assert!(diagnostics.contains(&MapDiagnostic::BrokenSpawnSlot {
+            slot: 0,
+            nexus: island
+        }));
+        assert!(diagnostics.iter().any(MapDiagnostic::is_error));
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gamerunner::{GameRunnerEvent, GameRunnerRes},
+    phasedriver::{MatchSettings, PhaseDriver, PhaseDurations},
+    runnercontrol::PausePolicy,
+    victory::VictoryCondition,
+    Cell, Force, GameState, LevelManagerRes, LocalPlayerRes, NodeTenant, Occupant, PhageType,
+};
+
+/// A player taking part in a match and the force they control
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PlayerSlot {
+    pub name: String,
+    pub force: Force,
+    /// Whether this player is controlled by this client
+    pub local: bool,
+    /// Whether this player is controlled by the computer
+    #[serde(default)]
+    pub bot: bool,
+}
+
+/// What every assigned force starts the match with
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct StartingResources {
+    pub qubits: u32,
+    /// Placed, in order, into the free cells next to each of the force's nexuses
+    pub phage: Vec<PhageType>,
+}
+
+impl Default for StartingResources {
+    fn default() -> Self {
+        StartingResources {
+            qubits: 50,
+            phage: vec![PhageType::Any],
+        }
+    }
+}
+
+/// What happens to the spawn slots of a map that no player was assigned to
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum UnfilledSlots {
+    /// The slot's nexus and territory are held by the neutral force
+    #[default]
+    Neutral,
+    /// The slot's nexus becomes an empty cell and its territory turns neutral
+    Remove,
+}
+
+/// The rules a match is played under
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RulesVariant {
+    /// Real time phases, the last force holding a nexus wins
+    #[default]
+    Standard,
+    /// Forces take turns, the last force holding a nexus wins
+    TurnBased,
+    /// Real time phases, the richest force after the given number of phases wins
+    Economic { after_phases: u32 },
+}
+
+impl RulesVariant {
+    pub fn victory(&self) -> VictoryCondition {
+        match self {
+            RulesVariant::Standard | RulesVariant::TurnBased => VictoryCondition::LastForceStanding,
+            RulesVariant::Economic { after_phases } => VictoryCondition::MostQubits {
+                after_phases: *after_phases,
+            },
+        }
+    }
+
+    pub fn settings(&self, networked: bool) -> MatchSettings {
+        MatchSettings {
+            driver: match self {
+                RulesVariant::TurnBased => PhaseDriver::TurnBased,
+                _ => PhaseDriver::RealTime {
+                    durations: PhaseDurations::default(),
+                },
+            },
+            pause: if networked {
+                PausePolicy::Vote
+            } else {
+                PausePolicy::Free
+            },
+        }
+    }
+}
+
+/// Everything needed to start a match, filled in by the lobby and consumed by the game runner
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct MatchConfig {
+    /// Map file in `assets/maps`
+    pub map: String,
+    pub players: Vec<PlayerSlot>,
+    pub starting: StartingResources,
+    pub rules: RulesVariant,
+    #[serde(default)]
+    pub unfilled: UnfilledSlots,
+}
+
+impl MatchConfig {
+    pub fn forces(&self) -> BTreeSet<Force> {
+        self.players.iter().map(|player| player.force).collect()
+    }
+
+    pub fn local_force(&self) -> Option<Force> {
+        self.players
+            .iter()
+            .find(|player| player.local)
+            .map(|player| player.force)
+    }
+
+    /// Whether any player is controlled by another client
+    pub fn networked(&self) -> bool {
+        self.players
+            .iter()
+            .any(|player| !player.local && !player.bot)
+    }
+
+    /// Prepares a freshly loaded map for this match: spawn slots of configured forces
+    /// get their starting occupants, the rest are handled as `unfilled` says
+    pub fn setup(&self, map: &GameState) -> GameState {
+        let mut state = map.clone();
+        state.victory = self.rules.victory();
+        state.refresh_spawn_slots();
+
+        let forces = self.forces();
+        for force in forces.iter() {
+            state.grant_qubits(*force, self.starting.qubits);
+        }
+
+        let (filled, unfilled): (Vec<_>, Vec<_>) = std::mem::take(&mut state.spawn_slots)
+            .into_iter()
+            .partition(|slot| forces.contains(&slot.force));
+
+        for slot in filled.iter() {
+            let placements: Vec<_> = if slot.occupants.is_empty() {
+                let mut free = state
+                    .get_all_neighbors(slot.nexus)
+                    .into_iter()
+                    .filter(|id| {
+                        let node = &state.nodes[id];
+                        node.can_be_occupied() && node.occupant().is_none()
+                    });
+                self.starting
+                    .phage
+                    .iter()
+                    .filter_map(|phage| free.next().map(|cell| (cell, *phage)))
+                    .collect()
+            } else {
+                slot.occupants.clone()
+            };
+
+            for (cell, phage) in placements {
+                let slot_mut = state
+                    .nodes
+                    .get_mut(&cell)
+                    .and_then(|node| node.occupant_slot_mut());
+                if let Some(occupant) = slot_mut {
+                    *occupant = Some(Occupant(slot.force, phage));
+                }
+            }
+        }
+
+        for slot in unfilled.iter() {
+            for node in state.nodes.values_mut() {
+                if node.force == slot.force {
+                    node.force = Force::NEUTRAL;
+                }
+                if node.id == slot.nexus && self.unfilled == UnfilledSlots::Remove {
+                    node.tenant = NodeTenant::Cell {
+                        cell: Cell { occupant: None },
+                    };
+                }
+                if let Some(occupant) = node.occupant_slot_mut() {
+                    if occupant.as_ref().is_some_and(|o| o.0 == slot.force) {
+                        *occupant = None;
+                    }
+                }
+            }
+        }
+
+        state.spawn_slots = filled;
+        state
+    }
+}
+
+/// Sets up the map of a configured match once it has loaded and starts the game runner
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn begin_match(
+    config: Option<Res<MatchConfig>>,
+    level_manager: Res<LevelManagerRes>,
+    mut maps: Query<(Entity, &mut GameState)>,
+    mut runner: ResMut<GameRunnerRes>,
+    mut local_player: ResMut<LocalPlayerRes>,
+    mut settings: ResMut<MatchSettings>,
+    mut runner_events: EventWriter<GameRunnerEvent>,
+    mut started: Local<Option<Entity>>,
+) {
+    let config = match config {
+        Some(config) => config,
+        None => {
+            *started = None;
+            return;
+        }
+    };
+
+    if level_manager.current_level.as_ref() != Some(&config.map) {
+        return;
+    }
+
+    let (entity, mut map) = match maps.get_single_mut() {
+        Ok(map) => map,
+        Err(_) => return,
+    };
+    if *started == Some(entity) {
+        return;
+    }
+
+    *started = Some(entity);
+    *map = config.setup(&map);
+    if let Some(force) = config.local_force() {
+        local_player.force = force;
+    }
+    *settings = config.rules.settings(config.networked());
+    *runner = GameRunnerRes {
+        run_game: true,
+        ..Default::default()
+    };
+
+    info!("Beginning match on {}", config.map);
+    runner_events.send(GameRunnerEvent::GameBegun);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{victory::surviving_forces, Nexus, NodeId, Vector};
+
+    use super::*;
+
+    #[test]
+    pub fn setup_grants_starting_resources() {
+        let mut map = GameState::new("Setup".to_string(), 2);
+        let nexus = map.create_node(Force(0), Vec3::ZERO);
+        let cell_1 = map.create_node(Force(0), Vec3::X);
+        let cell_2 = map.create_node(Force(0), Vec3::Z);
+        let enemy = map.create_node(Force(1), Vec3::Y);
+        for cell in [cell_1, cell_2] {
+            map.add_vector(Vector::new(nexus, cell)).unwrap();
+        }
+        for id in [nexus, enemy] {
+            map.nodes.get_mut(&id).unwrap().tenant = NodeTenant::Nexus {
+                nexus: Nexus::default(),
+            };
+        }
+
+        let config = MatchConfig {
+            map: "setup.json".to_string(),
+            players: vec![PlayerSlot {
+                name: "Player".to_string(),
+                force: Force(0),
+                local: true,
+                bot: false,
+            }],
+            starting: StartingResources {
+                qubits: 20,
+                phage: vec![PhageType::UV, PhageType::Sonic, PhageType::Electro],
+            },
+            rules: RulesVariant::Economic { after_phases: 30 },
+            unfilled: UnfilledSlots::Neutral,
+        };
+
+        let state = config.setup(&map);
+        assert_eq!(state.qubits(Force(0)), 20);
+        assert_eq!(state.qubits(Force(1)), 0);
+        assert_eq!(
+            state.victory,
+            VictoryCondition::MostQubits { after_phases: 30 }
+        );
+        assert_eq!(
+            state.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(0), PhageType::UV))
+        );
+        assert_eq!(
+            state.nodes[&NodeId(2)].occupant(),
+            Some(&Occupant(Force(0), PhageType::Sonic))
+        );
+    }
+
+    #[test]
+    pub fn unfilled_slots_turn_neutral_or_are_removed() {
+        let mut map = GameState::new("Slots".to_string(), 2);
+        let nexuses = [Force(0), Force(1)].map(|force| {
+            let nexus = map.create_node(force, Vec3::ZERO);
+            map.nodes.get_mut(&nexus).unwrap().tenant = NodeTenant::Nexus {
+                nexus: Nexus::default(),
+            };
+            nexus
+        });
+        let cell = map.create_node(Force(1), Vec3::X);
+        map.add_vector(Vector::new(nexuses[1], cell)).unwrap();
+        map.refresh_spawn_slots();
+        map.spawn_slots[1].occupants = vec![(cell, PhageType::Sonic)];
+
+        let mut config = MatchConfig {
+            map: "slots.json".to_string(),
+            players: vec![PlayerSlot {
+                name: "Bot".to_string(),
+                force: Force(1),
+                local: false,
+                bot: true,
+            }],
+            starting: StartingResources::default(),
+            rules: RulesVariant::Standard,
+            unfilled: UnfilledSlots::Neutral,
+        };
+        assert!(!config.networked());
+
+        let state = config.setup(&map);
+        assert_eq!(state.nodes[&nexuses[0]].force, Force::NEUTRAL);
+        assert_eq!(
+            state.nodes[&cell].occupant(),
+            Some(&Occupant(Force(1), PhageType::Sonic))
+        );
+        assert_eq!(state.spawn_slots.len(), 1);
+        assert_eq!(surviving_forces(&state), BTreeSet::from([Force(1)]));
+
+        config.unfilled = UnfilledSlots::Remove;
+        let state = config.setup(&map);
+        assert!(!matches!(
+            state.nodes[&nexuses[0]].tenant,
+            NodeTenant::Nexus { .. }
+        ));
+        assert_eq!(state.nodes[&nexuses[0]].force, Force::NEUTRAL);
+    }
+}
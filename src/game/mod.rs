@@ -1,21 +1,54 @@
+#[cfg(feature = "client")]
 use crate::util::{
     modelloading::{spawn_model, NodeTenentAssets},
     ColorPalette,
 };
 
+use self::combat::MatchupTable;
+#[cfg(feature = "client")]
 use self::controller::PlayerAction;
+use self::gamerunner::GamePhase;
+use self::mapinfo::MapMetadata;
+use self::trade::TradeRules;
+use self::validation::{mutated_tile, MutationRules};
+use self::victory::VictoryCondition;
+use self::visibility::FogRules;
+#[cfg(feature = "client")]
 use crate::game::settings::ReadWriteGameSettings;
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{prelude::*, reflect::TypeUuid};
+#[cfg(feature = "client")]
 use leafwing_input_manager::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
 
+pub mod checksum;
+pub mod combat;
+#[cfg(feature = "client")]
 pub mod controller;
+pub mod editor;
 pub mod gamerunner;
 pub mod map;
+pub mod mapgen;
+pub mod mapinfo;
+pub mod mapvalidation;
+pub mod matchconfig;
+#[cfg(feature = "client")]
 pub mod mutationinput;
+pub mod phasedriver;
+pub mod recombinator;
+pub mod replay;
+pub mod runnercontrol;
+#[cfg(feature = "client")]
 pub mod settings;
-
-#[derive(Component, Serialize, Deserialize, Clone)]
+pub mod simulation;
+pub mod trade;
+pub mod transmission;
+pub mod validation;
+pub mod victory;
+pub mod visibility;
+
+#[derive(Component, Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum Mutation {
     TriggerRecombinator {
         target: NodeId,
@@ -36,6 +69,18 @@ pub enum Mutation {
     },
 }
 
+impl Mutation {
+    /// Qubits the submitting force has to pay for this mutation
+    pub fn cost(&self) -> u32 {
+        match self {
+            Mutation::TriggerRecombinator { cost, .. }
+            | Mutation::AddVector { cost, .. }
+            | Mutation::RemoveVector { cost, .. }
+            | Mutation::ChangeReplicatorType { cost, .. } => *cost,
+        }
+    }
+}
+
 //Used to insert selected resource
 impl Default for Mutation {
     fn default() -> Self {
@@ -46,7 +91,7 @@ impl Default for Mutation {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub enum PhageType {
     UV,
     Electro,
@@ -71,7 +116,7 @@ pub enum PhageType {
 )]
 pub struct NodeId(u32);
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub enum RecombinatorEffect {
     DestroyPhageWithinRange(u32),
     GiveOccupierQubits { amt: u32 },
@@ -80,7 +125,7 @@ pub enum RecombinatorEffect {
     PullPhageForCombat(Vector),
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub enum RecombinatorTriggers {
     NumberOfTransmissionPhases { phases: u32 },
     PhageEntered,
@@ -92,14 +137,41 @@ pub enum RecombinatorTriggers {
     OpposingNeighbors,
 }
 
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct Recombinator {
-    trigger: RecombinatorTriggers,
-    effect: RecombinatorEffect,
+    pub trigger: RecombinatorTriggers,
+    pub effect: RecombinatorEffect,
+
+    #[serde(default)]
+    pub occupant: Option<Occupant>,
+
+    #[serde(default)]
+    pub observed: RecombinatorObservations,
+}
+
+/// Everything a recombinator has seen since it was last evaluated during the
+/// ReCombinate sub phase
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct RecombinatorObservations {
+    /// Transmission phases since this recombinator last triggered
+    pub phases_waited: u32,
+    pub phage_entered: bool,
+    pub phage_exited: bool,
+    pub combat_occured: bool,
+    /// The far end of every vector added to this recombinator
+    pub vectors_added: Vec<NodeId>,
+    /// The far end of every vector removed from this recombinator
+    pub vectors_removed: Vec<NodeId>,
+    /// Whether the occupier had enough qubits the last time this was evaluated,
+    /// the qubit trigger only fires when the threshold is first reached
+    pub qubits_reached: bool,
+    /// A player paid to fire this recombinator regardless of its trigger
+    #[serde(default)]
+    pub mutation_triggered: bool,
 }
 
 /// Describes a discrete location on the map that can be connected to other locations
-#[derive(Component, Serialize, Deserialize, Clone)]
+#[derive(Component, Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Node {
     pub id: NodeId,
     pub position: Vec3,
@@ -107,50 +179,110 @@ pub struct Node {
     pub tenant: NodeTenant,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+impl Node {
+    /// The phage currently occupying this node, if any
+    pub fn occupant(&self) -> Option<&Occupant> {
+        match &self.tenant {
+            NodeTenant::Cell { cell } => cell.occupant.as_ref(),
+            NodeTenant::Recombinator { recombinator } => recombinator.occupant.as_ref(),
+            NodeTenant::Generator { generator } => generator.occupant.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether phage are able to occupy this node
+    pub fn can_be_occupied(&self) -> bool {
+        matches!(
+            self.tenant,
+            NodeTenant::Cell { .. }
+                | NodeTenant::Recombinator { .. }
+                | NodeTenant::Generator { .. }
+        )
+    }
+
+    /// Mutable access to the occupant slot of this node. Returns None when the
+    /// tenant of this node cannot be occupied by phage at all
+    pub fn occupant_slot_mut(&mut self) -> Option<&mut Option<Occupant>> {
+        match &mut self.tenant {
+            NodeTenant::Cell { cell } => Some(&mut cell.occupant),
+            NodeTenant::Recombinator { recombinator } => Some(&mut recombinator.occupant),
+            NodeTenant::Generator { generator } => Some(&mut generator.occupant),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum NodeTenant {
     Cell { cell: Cell },
     Replicator { replicator: Replicator },
     Nexus { nexus: Nexus },
     Generator { generator: Generator },
-}
-pub enum CellType {
-    Transit,
     Recombinator { recombinator: Recombinator },
-    Generator { qubits_per_phase: u32 },
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct Cell {
-    occupant: Option<Occupant>,
+    pub occupant: Option<Occupant>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct Replicator {
-    output: PhageType,
+    pub output: PhageType,
 
     ///How many intervals/transmission phases this replicator takes to produce a phage
-    speed: u32,
+    pub speed: u32,
+
+    ///Transmission phases since this replicator last produced a phage
+    #[serde(default)]
+    pub phases_waited: u32,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Nexus {}
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Nexus {
+    /// How many attacking phage this nexus can absorb before it is captured
+    pub health: u32,
+}
 
-#[derive(Serialize, Deserialize, Clone)]
+impl Default for Nexus {
+    fn default() -> Self {
+        Nexus { health: 5 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct Generator {
     amt: u32,
     speed: u32,
+
+    /// Phage standing on the generator, its force is paid whenever the generator pays out
+    #[serde(default)]
+    pub occupant: Option<Occupant>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct VectorId(u32);
 
 /// Defines the team and occupying phage type
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Occupant(Force, PhageType);
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Occupant(pub Force, pub PhageType);
 
 /// Defines a relationship between two cells
-#[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(
+    Component,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Debug,
+    Default,
+)]
 pub struct Vector(pub NodeId, pub NodeId);
 
 impl Vector {
@@ -167,11 +299,109 @@ impl Vector {
     }
 }
 
+/// The vectors of a map, kept in order alongside an index of each node's neighbors.
+/// Serialized as a plain list so maps written before the index existed still load
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct VectorSet {
+    vectors: BTreeSet<Vector>,
+    adjacency: BTreeMap<NodeId, BTreeSet<NodeId>>,
+}
+
+impl VectorSet {
+    /// Adds the vector in its well formed orientation, returns false if it was already present
+    pub fn insert(&mut self, vector: Vector) -> bool {
+        let vector = Vector::new(vector.0, vector.1);
+        if !self.vectors.insert(vector) {
+            return false;
+        }
+
+        self.adjacency.entry(vector.0).or_default().insert(vector.1);
+        self.adjacency.entry(vector.1).or_default().insert(vector.0);
+        true
+    }
+
+    /// Removes the vector in either orientation, returns false if it was not present
+    pub fn remove(&mut self, vector: Vector) -> bool {
+        let vector = Vector::new(vector.0, vector.1);
+        if !self.vectors.remove(&vector) {
+            return false;
+        }
+
+        for (near, far) in [(vector.0, vector.1), (vector.1, vector.0)] {
+            if let Entry::Occupied(mut entry) = self.adjacency.entry(near) {
+                entry.get_mut().remove(&far);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+        true
+    }
+
+    pub fn contains(&self, vector: Vector) -> bool {
+        self.vectors.contains(&Vector::new(vector.0, vector.1))
+    }
+
+    /// Nodes sharing a vector with `id`, in ascending order
+    pub fn neighbors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.adjacency.get(&id).into_iter().flatten().copied()
+    }
+
+    pub fn degree(&self, id: NodeId) -> usize {
+        self.adjacency.get(&id).map_or(0, BTreeSet::len)
+    }
+
+    /// Vectors in ascending order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Vector> + '_ {
+        self.vectors.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+}
+
+impl FromIterator<Vector> for VectorSet {
+    fn from_iter<I: IntoIterator<Item = Vector>>(iter: I) -> Self {
+        let mut set = VectorSet::default();
+        for vector in iter {
+            set.insert(vector);
+        }
+        set
+    }
+}
+
+impl Serialize for VectorSet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.vectors.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for VectorSet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<Vector>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
 /// Defines a Force
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Force(u32);
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Force(pub u32);
 
 impl Force {
+    /// Owns whatever no player does, never eliminated and never wins
+    pub const NEUTRAL: Force = Force(u32::MAX);
+
+    pub fn is_neutral(&self) -> bool {
+        *self == Force::NEUTRAL
+    }
+
+    #[cfg(feature = "client")]
     pub fn color(&self) -> Color {
         match self.0 {
             0 => ColorPalette::ForceBlue.into(),
@@ -185,32 +415,205 @@ impl Force {
     }
 }
 
+/// Where a player starts: the nexus they defend and the phage already on the board.
+/// A slot's force owns the nexus and the territory around it in the map file
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SpawnSlot {
+    pub force: Force,
+    pub nexus: NodeId,
+    /// Placed when the slot is filled, instead of the match's starting phage
+    #[serde(default)]
+    pub occupants: Vec<(NodeId, PhageType)>,
+}
+
+/// Everything a player can submit to the game runner
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum GameMove {
+    /// Move the phage occupying `from` along the vector to `to`
     MovePhage {
-        vector: Vector,
+        from: NodeId,
+        to: NodeId,
     },
     Mutate {
         mutation: Mutation,
     },
+    /// Offer qubits to another force, transferred at the start of the next transmission phase
     GiveQubits {
         source: Force,
         dest: Force,
         qty: u32,
     },
+    /// Accept the qubits `source` offered this phase, when the map requires acceptance
+    AcceptQubits {
+        source: Force,
+    },
 }
 
 /// Data Only representation of a Game Map, Game acts as a pure state-machine
-#[derive(Component, Serialize, Deserialize)]
+#[derive(Component, TypeUuid, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[uuid = "5d1b6a9e-3f0c-4c8e-9a57-2e4b8f61c7d3"]
 pub struct GameState {
-    pub nodes: HashMap<NodeId, Node>,
-    //connections: HashMap<NodeId, Vec<VectorId>>,
-    pub vectors: Vec<Vector>,
+    /// Version of the map format this state was written in, see [`map::MAP_FORMAT_VERSION`]
+    #[serde(default)]
+    pub format_version: u32,
+
+    pub nodes: BTreeMap<NodeId, Node>,
+    pub vectors: VectorSet,
     pub num_players: u32,
     pub name: String,
     pub next_free_id: NodeId,
+
+    /// Number of transmission phases that have been run against this state
+    #[serde(default)]
+    pub transmission_phases: u32,
+
+    /// Decides the victor of contested cells, maps without one use the default table
+    #[serde(default)]
+    pub matchups: MatchupTable,
+
+    /// How many qubits each force currently owns
+    #[serde(default)]
+    pub qubits: BTreeMap<Force, u32>,
+
+    #[serde(default)]
+    pub victory: VictoryCondition,
+
+    /// Forces that have lost their last nexus
+    #[serde(default)]
+    pub eliminated: BTreeSet<Force>,
+
+    #[serde(default)]
+    pub mutation_rules: MutationRules,
+
+    /// Tiles each force has already mutated since the last transmission phase
+    #[serde(default)]
+    pub mutated_tiles: BTreeSet<(Force, NodeId)>,
+
+    #[serde(default)]
+    pub trade_rules: TradeRules,
+
+    #[serde(default)]
+    pub metadata: MapMetadata,
+
+    /// Starting positions players are assigned to, in the order the lobby lists them
+    #[serde(default)]
+    pub spawn_slots: Vec<SpawnSlot>,
+
+    #[serde(default)]
+    pub fog: FogRules,
 }
 
 impl GameState {
+    /// An empty map with no nodes or vectors
+    pub fn new(name: String, num_players: u32) -> Self {
+        GameState {
+            format_version: map::MAP_FORMAT_VERSION,
+            nodes: BTreeMap::new(),
+            vectors: VectorSet::default(),
+            num_players,
+            name,
+            next_free_id: NodeId(0),
+            transmission_phases: 0,
+            matchups: MatchupTable::default(),
+            qubits: BTreeMap::new(),
+            victory: VictoryCondition::default(),
+            eliminated: BTreeSet::new(),
+            mutation_rules: MutationRules::default(),
+            mutated_tiles: BTreeSet::new(),
+            trade_rules: TradeRules::default(),
+            metadata: MapMetadata::for_players(num_players),
+            spawn_slots: Vec::default(),
+            fog: FogRules::default(),
+        }
+    }
+
+    /// Node ids in ascending order
+    pub fn node_ids_in_order(&self) -> Vec<NodeId> {
+        self.nodes.keys().copied().collect()
+    }
+
+    pub fn qubits(&self, force: Force) -> u32 {
+        self.qubits.get(&force).copied().unwrap_or(0)
+    }
+
+    pub fn grant_qubits(&mut self, force: Force, qty: u32) {
+        let balance = self.qubits.entry(force).or_insert(0);
+        *balance = balance.saturating_add(qty);
+    }
+
+    pub fn spend_qubits(&mut self, force: Force, qty: u32) -> Result<(), PlayerActionError> {
+        let available = self.qubits(force);
+        if available < qty {
+            return Err(PlayerActionError::InsufficientQubits {
+                cost: qty,
+                available,
+            });
+        }
+
+        self.qubits.insert(force, available - qty);
+        Ok(())
+    }
+
+    /// Applies a mutation submitted by `force`, charging its cost when it succeeds
+    pub fn apply_mutation(
+        &mut self,
+        force: Force,
+        mutation: &Mutation,
+    ) -> Result<(), PlayerActionError> {
+        let available = self.qubits(force);
+        if available < mutation.cost() {
+            return Err(PlayerActionError::InsufficientQubits {
+                cost: mutation.cost(),
+                available,
+            });
+        }
+
+        match mutation {
+            //Recombinators Trigger at the beginning of the next interval
+            Mutation::TriggerRecombinator { target, .. } => {
+                if !self.nodes.contains_key(target) {
+                    return Err(PlayerActionError::NodeIdDoesNotExist(*target));
+                }
+
+                self.recombinator_observations_mut(*target)
+                    .ok_or(PlayerActionError::NotARecombinator(*target))?
+                    .mutation_triggered = true;
+            }
+
+            //Vectors are removed at time of mutation
+            Mutation::RemoveVector { relation, .. } => {
+                self.remove_vector(*relation)?;
+                self.observe_vector_change(*relation, false);
+            }
+
+            //Vectors are added at time of mutation
+            Mutation::AddVector { relation, .. } => {
+                self.add_vector(*relation)?;
+                self.observe_vector_change(*relation, true);
+            }
+
+            //Replicator Output is changed at time of mutation and counter is reset
+            Mutation::ChangeReplicatorType {
+                replicator: id,
+                new_type,
+                ..
+            } => match self.nodes.get_mut(id).map(|node| &mut node.tenant) {
+                Some(NodeTenant::Replicator { replicator }) => {
+                    replicator.output = *new_type;
+                    replicator.phases_waited = 0;
+                }
+                Some(_) => return Err(PlayerActionError::NotAReplicator(*id)),
+                None => return Err(PlayerActionError::NodeIdDoesNotExist(*id)),
+            },
+        }
+
+        if let Ok(tile) = mutated_tile(self, force, mutation) {
+            self.mutated_tiles.insert((force, tile));
+        }
+
+        self.spend_qubits(force, mutation.cost())
+    }
+
     pub fn create_node(&mut self, force: Force, position: Vec3) -> NodeId {
         self.nodes.insert(
             self.next_free_id,
@@ -229,7 +632,52 @@ impl GameState {
         to_return
     }
 
+    /// Keeps a spawn slot for every nexus owned by a player force: slots whose nexus is
+    /// gone are dropped, the rest follow the nexus' force and new nexuses get new slots
+    pub fn refresh_spawn_slots(&mut self) {
+        let nodes = &self.nodes;
+        let is_spawn = |id: &NodeId| {
+            nodes.get(id).is_some_and(|node| {
+                matches!(node.tenant, NodeTenant::Nexus { .. }) && !node.force.is_neutral()
+            })
+        };
+
+        self.spawn_slots.retain(|slot| is_spawn(&slot.nexus));
+        for slot in self.spawn_slots.iter_mut() {
+            slot.force = nodes[&slot.nexus].force;
+            slot.occupants.retain(|(id, _)| nodes.contains_key(id));
+        }
+
+        for id in self.node_ids_in_order() {
+            if is_spawn(&id) && !self.spawn_slots.iter().any(|slot| slot.nexus == id) {
+                self.spawn_slots.push(SpawnSlot {
+                    force: self.nodes[&id].force,
+                    nexus: id,
+                    occupants: Vec::default(),
+                });
+            }
+        }
+    }
+
+    /// Removes a node along with every vector connected to it
+    pub fn remove_node(&mut self, id: NodeId) -> Result<Node, PlayerActionError> {
+        let node = self
+            .nodes
+            .remove(&id)
+            .ok_or(PlayerActionError::NodeIdDoesNotExist(id))?;
+
+        for neighbor in self.get_all_neighbors(id) {
+            self.vectors.remove(Vector::new(id, neighbor));
+        }
+
+        Ok(node)
+    }
+
     pub fn add_vector(&mut self, vector: Vector) -> Result<(), PlayerActionError> {
+        if vector.0 == vector.1 {
+            return Err(PlayerActionError::SelfLoop(vector.0));
+        }
+
         if self.vector_exists(vector) {
             return Err(PlayerActionError::VectorExists);
         }
@@ -242,53 +690,74 @@ impl GameState {
             return Err(PlayerActionError::NodeIdDoesNotExist(vector.1));
         }
 
-        //Swap in place so the lowest node id is first
-        if vector.0 > vector.1 {
-            self.vectors.push(Vector(vector.1, vector.0));
-        } else {
-            self.vectors.push(vector);
-        }
+        //Stored with the lowest node id first
+        self.vectors.insert(vector);
 
         Ok(())
     }
 
     pub fn vector_exists(&self, vector: Vector) -> bool {
-        if vector.0 > vector.1 {
-            self.vectors.contains(&Vector(vector.1, vector.0))
-        } else {
-            self.vectors.contains(&vector)
-        }
+        self.vectors.contains(vector)
     }
 
     pub fn remove_vector(&mut self, to_remove: Vector) -> Result<(), PlayerActionError> {
-        if self.vector_exists(to_remove) {
-            // https://stackoverflow.com/questions/26243025/remove-an-element-from-a-vector
+        match self.vectors.remove(to_remove) {
+            true => Ok(()),
+            false => Err(PlayerActionError::VectorDoesNotExist { vector: to_remove }),
+        }
+    }
 
-            match self.vectors.iter().position(|item| to_remove == *item) {
-                Some(index) => {
-                    self.vectors.remove(index); // No indexes on this!
-                    return Ok(());
+    /// Neighbors of `id` in ascending order, looked up from the adjacency index
+    pub fn get_all_neighbors(&self, id: NodeId) -> Vec<NodeId> {
+        self.vectors.neighbors(id).collect()
+    }
+
+    /// Number of vector hops from every reachable node to the closest of `sources`
+    pub fn hops_from(&self, sources: impl IntoIterator<Item = NodeId>) -> BTreeMap<NodeId, u32> {
+        let mut hops = BTreeMap::new();
+        let mut queue = VecDeque::new();
+
+        for source in sources {
+            hops.insert(source, 0);
+            queue.push_back(source);
+        }
+
+        while let Some(id) = queue.pop_front() {
+            let distance = hops[&id];
+            for neighbor in self.get_all_neighbors(id) {
+                if let Entry::Vacant(entry) = hops.entry(neighbor) {
+                    entry.insert(distance + 1);
+                    queue.push_back(neighbor);
                 }
-                None => return Err(PlayerActionError::VectorDoesNotExist { vector: to_remove }),
             }
         }
 
-        Err(PlayerActionError::VectorDoesNotExist { vector: to_remove })
+        hops
     }
 
-    pub fn get_all_neighbors(&self, id: NodeId) -> Vec<NodeId> {
-        let mut to_return = Vec::default();
-        for vector in self.vectors.iter() {
-            if vector.0 == id {
-                to_return.push(vector.1);
-            }
-            if vector.1 == id {
-                to_return.push(vector.0);
+    pub fn recombinator_observations_mut(
+        &mut self,
+        id: NodeId,
+    ) -> Option<&mut RecombinatorObservations> {
+        match self.nodes.get_mut(&id).map(|node| &mut node.tenant) {
+            Some(NodeTenant::Recombinator { recombinator }) => Some(&mut recombinator.observed),
+            _ => None,
+        }
+    }
+
+    /// Records a vector change against any recombinator on either end of it
+    fn observe_vector_change(&mut self, vector: Vector, added: bool) {
+        for (near, far) in [(vector.0, vector.1), (vector.1, vector.0)] {
+            if let Some(observed) = self.recombinator_observations_mut(near) {
+                match added {
+                    true => observed.vectors_added.push(far),
+                    false => observed.vectors_removed.push(far),
+                }
             }
         }
-        to_return
     }
 
+    #[cfg(feature = "client")]
     pub fn spawn_node(
         &self,
         node: &Node,
@@ -311,20 +780,106 @@ impl GameState {
             NodeTenant::Generator { generator: _ } => {
                 spawn_model(commands, node_assets.cell.clone(), meshes, node)
             }
+            NodeTenant::Recombinator { recombinator: _ } => {
+                spawn_model(commands, node_assets.recombinator.clone(), meshes, node)
+            }
         }
     }
 }
 
+#[derive(Default)]
 pub struct LevelManagerRes {
     pub current_level: Option<String>,
 }
 
-#[derive(Debug)]
+/// The force controlled by this client
+pub struct LocalPlayerRes {
+    pub force: Force,
+}
+
+impl Default for LocalPlayerRes {
+    fn default() -> Self {
+        LocalPlayerRes { force: Force(0) }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum PlayerActionError {
     VectorExists,
     VectorDoesNotExist { vector: Vector },
     BadVectorFormat,
     NodeIdDoesNotExist(NodeId),
+    InsufficientQubits { cost: u32, available: u32 },
+    WrongPhase(GamePhase),
+    ForceEliminated(Force),
+    NotControlledBy { node: NodeId, force: Force },
+    TileAlreadyMutated(NodeId),
+    SelfLoop(NodeId),
+    VectorTooLong { length: f32, max: f32 },
+    TooManyVectors { node: NodeId, max: usize },
+    NotARecombinator(NodeId),
+    NotAReplicator(NodeId),
+    NotYourQubits(Force),
+    InvalidTrade { source: Force, dest: Force },
+    NoTradeOffered { source: Force },
+}
+
+impl fmt::Display for PlayerActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerActionError::VectorExists => write!(f, "Those nodes are already connected"),
+            PlayerActionError::VectorDoesNotExist { vector } => {
+                write!(f, "Vector {:?} does not exist", vector)
+            }
+            PlayerActionError::BadVectorFormat => write!(f, "That is not a valid vector"),
+            PlayerActionError::NodeIdDoesNotExist(id) => write!(f, "Node {:?} does not exist", id),
+            PlayerActionError::InsufficientQubits { cost, available } => write!(
+                f,
+                "That costs {} qubits but only {} are available",
+                cost, available
+            ),
+            PlayerActionError::WrongPhase(phase) => {
+                write!(f, "That can not be done during the {:?}", phase)
+            }
+            PlayerActionError::ForceEliminated(force) => {
+                write!(f, "Player {} has been eliminated", force.0 + 1)
+            }
+            PlayerActionError::NotControlledBy { node, force } => write!(
+                f,
+                "Node {:?} is not controlled by player {}",
+                node,
+                force.0 + 1
+            ),
+            PlayerActionError::TileAlreadyMutated(id) => {
+                write!(f, "Node {:?} was already mutated this phase", id)
+            }
+            PlayerActionError::SelfLoop(id) => write!(f, "Node {:?} can not connect to itself", id),
+            PlayerActionError::VectorTooLong { length, max } => write!(
+                f,
+                "That vector is {:.1} long, vectors can be at most {:.1}",
+                length, max
+            ),
+            PlayerActionError::TooManyVectors { node, max } => {
+                write!(f, "Node {:?} already has {} vectors", node, max)
+            }
+            PlayerActionError::NotARecombinator(id) => {
+                write!(f, "Node {:?} is not a recombinator", id)
+            }
+            PlayerActionError::NotAReplicator(id) => write!(f, "Node {:?} is not a replicator", id),
+            PlayerActionError::NotYourQubits(force) => {
+                write!(f, "Those qubits belong to player {}", force.0 + 1)
+            }
+            PlayerActionError::InvalidTrade { source, dest } => write!(
+                f,
+                "Player {} can not trade with player {}",
+                source.0 + 1,
+                dest.0 + 1
+            ),
+            PlayerActionError::NoTradeOffered { source } => {
+                write!(f, "Player {} has not offered a trade", source.0 + 1)
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -336,8 +891,11 @@ pub struct PlayerMutationEvent {
 #[derive(Clone, Serialize, Deserialize)]
 pub struct MutationFailed {
     pub mutation: Mutation,
+    pub force: Force,
+    pub reason: PlayerActionError,
 }
 
+#[cfg(feature = "client")]
 pub fn spawn_player(
     mut commands: Commands,
     game_settings: ResMut<ReadWriteGameSettings>,
@@ -4,7 +4,7 @@ use leafwing_input_manager::prelude::ActionState;
 
 use crate::game::{Force, Mutation};
 
-use super::{controller::PlayerAction, NodeId, PlayerMutationEvent, Vector};
+use super::{controller::PlayerAction, LocalPlayerRes, NodeId, PlayerMutationEvent, Vector};
 
 //TODO it would be nice to have an enum here instead of a dummy mutation
 //the internal values of the mutation aren't used and are just dummy values here
@@ -53,6 +53,7 @@ pub fn mutation_input(
     mut picking_events: EventReader<PickingEvent>,
     nodes: Query<&crate::game::Node>,
     selected: Res<MutationSelection>,
+    local_player: Res<LocalPlayerRes>,
     //TODO use a local to track the internal state of previous clicks for multi click events
     mut previous_click: Local<Option<NodeId>>,
 ) {
@@ -71,7 +72,7 @@ pub fn mutation_input(
                                 target: node.id,
                                 cost: 10,
                             },
-                            force: node.force.clone(),
+                            force: local_player.force,
                         });
                     }
                     Mutation::AddVector { .. } => {
@@ -85,7 +86,7 @@ pub fn mutation_input(
                                     relation: Vector::new(prev_id, node.id),
                                     cost: 10,
                                 },
-                                force: node.force.clone(),
+                                force: local_player.force,
                             });
                             *previous_click = None;
                         } else {
@@ -107,7 +108,7 @@ pub fn mutation_input(
                                     relation: Vector::new(prev_id, node.id),
                                     cost: 10,
                                 },
-                                force: node.force.clone(),
+                                force: local_player.force,
                             });
                             *previous_click = None;
                         } else {
@@ -131,7 +132,7 @@ pub fn mutation_input(
                                 new_type: crate::game::PhageType::UV,
                                 cost: 10,
                             },
-                            force: node.force.clone(),
+                            force: local_player.force,
                         });
                     }
                 }
@@ -0,0 +1,247 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+#[cfg(feature = "client")]
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client")]
+use super::{controller::PlayerAction, LocalPlayerRes};
+use super::{
+    gamerunner::{AdvanceGamePhaseEvent, GamePhase, GameRunnerRes, GameRunnerSystem},
+    runnercontrol::PausePolicy,
+    victory::surviving_forces,
+    Force, GameState,
+};
+
+/// How long each phase lasts, in seconds, when the game is played in real time
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PhaseDurations {
+    pub mutation: f32,
+    pub interstitial: f32,
+    pub transmission: f32,
+}
+
+impl Default for PhaseDurations {
+    fn default() -> Self {
+        PhaseDurations {
+            mutation: 10.,
+            interstitial: 1.,
+            transmission: 2.,
+        }
+    }
+}
+
+impl PhaseDurations {
+    pub fn of(&self, phase: GamePhase) -> f32 {
+        match phase {
+            GamePhase::MutationPhase => self.mutation,
+            GamePhase::InterstitialPhase => self.interstitial,
+            GamePhase::TransmissionPhase => self.transmission,
+        }
+    }
+}
+
+/// Decides when the game runner moves on to the next phase
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum PhaseDriver {
+    /// Every phase lasts a fixed amount of time
+    RealTime { durations: PhaseDurations },
+
+    /// The mutation phase lasts until every surviving force has ended its turn,
+    /// the other phases pass as soon as they have run
+    TurnBased,
+}
+
+impl Default for PhaseDriver {
+    fn default() -> Self {
+        PhaseDriver::RealTime {
+            durations: PhaseDurations::default(),
+        }
+    }
+}
+
+/// Settings for the match being played
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct MatchSettings {
+    pub driver: PhaseDriver,
+    pub pause: PausePolicy,
+}
+
+/// A force is done submitting moves for this mutation phase
+#[derive(Clone, Copy, Debug)]
+pub struct EndTurnEvent {
+    pub force: Force,
+}
+
+/// Adds the systems that send [`AdvanceGamePhaseEvent`] for the configured [`PhaseDriver`]
+pub struct PhaseDriverPlugin;
+
+impl Plugin for PhaseDriverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchSettings>();
+        app.add_event::<EndTurnEvent>();
+        #[cfg(feature = "client")]
+        app.add_system(end_turn_input);
+        app.add_system(
+            real_time_driver
+                .run_if(should_drive)
+                .run_if(is_real_time)
+                .before(GameRunnerSystem::RunGame),
+        );
+        app.add_system(
+            turn_based_driver
+                .run_if(should_drive)
+                .run_if_not(is_real_time)
+                .before(GameRunnerSystem::RunGame),
+        );
+    }
+}
+
+fn should_drive(runner: Res<GameRunnerRes>) -> bool {
+    runner.run_game && !runner.paused
+}
+
+fn is_real_time(settings: Res<MatchSettings>) -> bool {
+    matches!(settings.driver, PhaseDriver::RealTime { .. })
+}
+
+#[cfg(feature = "client")]
+fn end_turn_input(
+    actions: Query<&ActionState<PlayerAction>>,
+    local_player: Option<Res<LocalPlayerRes>>,
+    mut end_turn: EventWriter<EndTurnEvent>,
+) {
+    if let (Ok(actions), Some(local_player)) = (actions.get_single(), local_player) {
+        if actions.just_pressed(PlayerAction::EndTurn) {
+            end_turn.send(EndTurnEvent {
+                force: local_player.force,
+            });
+        }
+    }
+}
+
+fn real_time_driver(
+    time: Res<Time>,
+    settings: Res<MatchSettings>,
+    runner: Res<GameRunnerRes>,
+    mut advance: EventWriter<AdvanceGamePhaseEvent>,
+    mut timer: Local<(Option<GamePhase>, f32)>,
+) {
+    let durations = match &settings.driver {
+        PhaseDriver::RealTime { durations } => durations,
+        PhaseDriver::TurnBased => return,
+    };
+
+    let (phase, elapsed) = &mut *timer;
+    if *phase != Some(runner.game_phase) {
+        *phase = Some(runner.game_phase);
+        *elapsed = 0.;
+    }
+
+    *elapsed += time.delta_seconds() * runner.speed;
+    if *elapsed >= durations.of(runner.game_phase) {
+        // Cleared so the next phase starts counting from zero even if it matches this one
+        *phase = None;
+        advance.send(AdvanceGamePhaseEvent {});
+    }
+}
+
+fn turn_based_driver(
+    runner: Res<GameRunnerRes>,
+    maps: Query<&GameState>,
+    mut end_turn: EventReader<EndTurnEvent>,
+    mut advance: EventWriter<AdvanceGamePhaseEvent>,
+    mut ended: Local<BTreeSet<Force>>,
+) {
+    if runner.game_phase != GamePhase::MutationPhase {
+        end_turn.clear();
+        ended.clear();
+        advance.send(AdvanceGamePhaseEvent {});
+        return;
+    }
+
+    ended.extend(end_turn.iter().map(|ev| ev.force));
+
+    let map = match maps.get_single() {
+        Ok(map) => map,
+        Err(_) => return,
+    };
+
+    let forces = surviving_forces(map);
+    if !forces.is_empty() && forces.is_subset(&ended) {
+        ended.clear();
+        advance.send(AdvanceGamePhaseEvent {});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{
+        gamerunner::{GameRunnerEvent, MacroPhageGamerunnerPlugin},
+        Nexus, NodeTenant,
+    };
+
+    use super::*;
+
+    #[test]
+    pub fn turn_based_driver_waits_for_every_force() {
+        let mut map = GameState::new("Turns".to_string(), 2);
+        for force in [Force(0), Force(1)] {
+            let id = map.create_node(force, Vec3::ZERO);
+            map.nodes.get_mut(&id).unwrap().tenant = NodeTenant::Nexus {
+                nexus: Nexus::default(),
+            };
+        }
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(MacroPhageGamerunnerPlugin {})
+            .insert_resource(MatchSettings {
+                driver: PhaseDriver::TurnBased,
+                ..Default::default()
+            });
+        app.world.spawn().insert(map);
+        app.world.resource_mut::<GameRunnerRes>().run_game = true;
+
+        app.world.send_event(EndTurnEvent { force: Force(0) });
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(
+            app.world.resource::<GameRunnerRes>().game_phase,
+            GamePhase::MutationPhase
+        );
+
+        app.world.send_event(EndTurnEvent { force: Force(1) });
+        app.update();
+        assert_eq!(
+            app.world.resource::<GameRunnerRes>().game_phase,
+            GamePhase::InterstitialPhase
+        );
+
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(
+            app.world.resource::<GameRunnerRes>().game_phase,
+            GamePhase::MutationPhase
+        );
+
+        let runner_events = app.world.resource::<Events<GameRunnerEvent>>();
+        let phases: Vec<_> = runner_events
+            .get_reader()
+            .iter(runner_events)
+            .cloned()
+            .collect();
+        assert!(phases.contains(&GameRunnerEvent::PhaseEntered {
+            phase: GamePhase::MutationPhase
+        }));
+        assert!(phases.contains(&GameRunnerEvent::PhaseExited {
+            phase: GamePhase::TransmissionPhase
+        }));
+    }
+}
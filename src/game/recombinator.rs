@@ -0,0 +1,190 @@
+use std::collections::{BTreeSet, HashSet};
+
+use super::{
+    gamerunner::TransmissionEvents, transmission::Contest, GameState, Node, NodeId, NodeTenant,
+    PhageType, Recombinator, RecombinatorEffect, RecombinatorObservations, RecombinatorTriggers,
+};
+
+/// Evaluates every recombinator, in order of map position, and applies the effect of each
+/// one that triggered. Cells whose occupier was given an advantage are added to `advantaged`
+/// and the returned contests are phage pulled into combat by a recombinator
+pub(crate) fn recombinate(
+    state: &mut GameState,
+    advantaged: &mut BTreeSet<NodeId>,
+    events: &mut Vec<TransmissionEvents>,
+) -> Vec<Contest> {
+    let mut pulled = Vec::default();
+
+    for id in recombinators_in_order(state) {
+        let recombinator = match &state.nodes[&id].tenant {
+            NodeTenant::Recombinator { recombinator } => recombinator.clone(),
+            _ => continue,
+        };
+
+        let (triggered, observed) = evaluate(state, id, &recombinator);
+        *state.recombinator_observations_mut(id).unwrap() = observed;
+
+        if triggered {
+            events.push(TransmissionEvents::RecombinatorTriggered {
+                cell: id,
+                recombinator: recombinator.clone(),
+            });
+            apply_effect(
+                state,
+                id,
+                &recombinator.effect,
+                advantaged,
+                &mut pulled,
+                events,
+            );
+        }
+    }
+
+    pulled
+}
+
+/// Recombinators sorted by position, then id, so every client activates them in the same order
+fn recombinators_in_order(state: &GameState) -> Vec<NodeId> {
+    let mut recombinators: Vec<&Node> = state
+        .nodes
+        .values()
+        .filter(|node| matches!(node.tenant, NodeTenant::Recombinator { .. }))
+        .collect();
+
+    recombinators.sort_by(|a, b| {
+        a.position
+            .x
+            .total_cmp(&b.position.x)
+            .then(a.position.y.total_cmp(&b.position.y))
+            .then(a.position.z.total_cmp(&b.position.z))
+            .then(a.id.cmp(&b.id))
+    });
+
+    recombinators.into_iter().map(|node| node.id).collect()
+}
+
+/// Decides whether a recombinator triggers this phase, returning the observations it
+/// should carry into the next phase
+fn evaluate(
+    state: &GameState,
+    id: NodeId,
+    recombinator: &Recombinator,
+) -> (bool, RecombinatorObservations) {
+    let observed = &recombinator.observed;
+    let phases_waited = observed.phases_waited + 1;
+    let qubits_reached = match (&recombinator.trigger, recombinator.occupant) {
+        (RecombinatorTriggers::Qubits { qty }, Some(occupant)) => state.qubits(occupant.0) >= *qty,
+        _ => false,
+    };
+
+    let triggered = match &recombinator.trigger {
+        RecombinatorTriggers::NumberOfTransmissionPhases { phases } => phases_waited >= *phases,
+        RecombinatorTriggers::PhageEntered => observed.phage_entered,
+        RecombinatorTriggers::PhageExited => observed.phage_exited,
+        RecombinatorTriggers::CombatOccured => observed.combat_occured,
+        RecombinatorTriggers::Qubits { .. } => qubits_reached && !observed.qubits_reached,
+        RecombinatorTriggers::VectorAdded { dest } => observed.vectors_added.contains(dest),
+        RecombinatorTriggers::VectorRemoved { dest } => observed.vectors_removed.contains(dest),
+        RecombinatorTriggers::OpposingNeighbors => has_opposing_neighbors(state, id),
+    } || observed.mutation_triggered;
+
+    let next = RecombinatorObservations {
+        phases_waited: if triggered { 0 } else { phases_waited },
+        qubits_reached,
+        ..Default::default()
+    };
+
+    (triggered, next)
+}
+
+/// True when phage from more than one force occupy the recombinator and its neighbors
+fn has_opposing_neighbors(state: &GameState, id: NodeId) -> bool {
+    let mut cells = state.get_all_neighbors(id);
+    cells.push(id);
+
+    let forces: HashSet<_> = cells
+        .iter()
+        .filter_map(|cell| state.nodes.get(cell).and_then(|node| node.occupant()))
+        .map(|occupant| occupant.0)
+        .collect();
+
+    forces.len() > 1
+}
+
+fn apply_effect(
+    state: &mut GameState,
+    id: NodeId,
+    effect: &RecombinatorEffect,
+    advantaged: &mut BTreeSet<NodeId>,
+    pulled: &mut Vec<Contest>,
+    events: &mut Vec<TransmissionEvents>,
+) {
+    let occupier = state.nodes[&id].occupant().copied();
+
+    match effect {
+        RecombinatorEffect::DestroyPhageWithinRange(range) => {
+            let hops = state.hops_from([id]);
+            for (cell, distance) in hops {
+                if distance <= *range {
+                    destroy_occupant(state, cell, events);
+                }
+            }
+        }
+        RecombinatorEffect::GiveOccupierQubits { amt } => {
+            if let Some(occupier) = occupier {
+                state.grant_qubits(occupier.0, *amt);
+                events.push(TransmissionEvents::QubitsDistributed {
+                    recipient: occupier.0,
+                    qty: *amt,
+                    source: id,
+                });
+            }
+        }
+        RecombinatorEffect::GiveOccupierAdvantage => {
+            if occupier.is_some() {
+                advantaged.insert(id);
+            }
+        }
+        RecombinatorEffect::DestroyOccupierIfType { phage_type } => {
+            if let Some(occupier) = occupier {
+                if occupier.1 == *phage_type || *phage_type == PhageType::Any {
+                    destroy_occupant(state, id, events);
+                }
+            }
+        }
+        RecombinatorEffect::PullPhageForCombat(vector) => {
+            let far_end = match (vector.0 == id, vector.1 == id) {
+                (true, _) => vector.1,
+                (_, true) => vector.0,
+                _ => return,
+            };
+            if !state.vector_exists(*vector) {
+                return;
+            }
+
+            if let Some(phage) = state.nodes.get(&far_end).and_then(|node| node.occupant()) {
+                if occupier.map(|occupier| occupier.0) != Some(phage.0) {
+                    pulled.push(Contest {
+                        cell: id,
+                        attackers: vec![(far_end, *phage)],
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn destroy_occupant(state: &mut GameState, cell: NodeId, events: &mut Vec<TransmissionEvents>) {
+    if let Some(slot) = state
+        .nodes
+        .get_mut(&cell)
+        .and_then(|node| node.occupant_slot_mut())
+    {
+        if let Some(phage) = slot.take() {
+            events.push(TransmissionEvents::PhageDestroyed {
+                cell,
+                phage: (phage.0, phage.1),
+            });
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    checksum::StateChecksum,
+    gamerunner::{
+        GameMoveAccepted, GamePhase, GameRunnerEvent, GameRunnerSystem, TransmissionEvents,
+    },
+    matchconfig::MatchConfig,
+    simulation::Simulation,
+    Force, GameMove, GameState,
+};
+
+/// Bumped whenever the replay format, or the rules it replays under, change incompatibly
+pub const REPLAY_VERSION: u32 = 1;
+
+/// A move the game runner accepted
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RecordedMove {
+    pub force: Force,
+    pub game_move: GameMove,
+}
+
+/// Every move accepted during a single phase, in the order they were accepted
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RecordedPhase {
+    pub phase: GamePhase,
+    pub moves: Vec<RecordedMove>,
+    /// Checksum of the state on entering this phase, recorded for transmission phases
+    #[serde(default)]
+    pub checksum: Option<u64>,
+}
+
+/// Everything needed to reproduce a match: the map as it was when the match began and
+/// the moves accepted in each phase after that
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub version: u32,
+    pub config: Option<MatchConfig>,
+    pub initial_state: GameState,
+    pub phases: Vec<RecordedPhase>,
+}
+
+impl Replay {
+    pub fn new(config: Option<MatchConfig>, initial_state: GameState) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            config,
+            initial_state,
+            phases: vec![RecordedPhase {
+                phase: GamePhase::MutationPhase,
+                moves: Vec::default(),
+                checksum: None,
+            }],
+        }
+    }
+
+    /// Replays the first `phases` recorded phases from the initial state
+    pub fn simulate(&self, phases: usize) -> Simulation {
+        let mut sim = Simulation::new(self.initial_state.clone());
+        for recorded in self.phases.iter().take(phases) {
+            self.replay_phase(&mut sim, recorded);
+        }
+        sim
+    }
+
+    /// Submits the moves of a recorded phase and advances past it
+    pub fn replay_phase(
+        &self,
+        sim: &mut Simulation,
+        recorded: &RecordedPhase,
+    ) -> Vec<TransmissionEvents> {
+        for recorded_move in recorded.moves.iter() {
+            if let Err(err) = sim.submit(recorded_move.force, &recorded_move.game_move) {
+                warn!("Replayed move was rejected: {:?}", err);
+            }
+        }
+        sim.advance()
+    }
+
+    /// Whether `sim`, after replaying the phase before `phase`, matches the recorded
+    /// checksum of `phase`. Phases without a checksum always match
+    pub fn matches_recording(&self, phase: usize, sim: &Simulation) -> bool {
+        match self
+            .phases
+            .get(phase)
+            .and_then(|recorded| recorded.checksum)
+        {
+            Some(checksum) => StateChecksum::of(&sim.state).total == checksum,
+            None => true,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(serde_json::to_writer(writer, self)?)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let reader = BufReader::new(File::open(path)?);
+        let replay: Replay = serde_json::from_reader(reader)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "replay version {} is not supported, expected {}",
+                    replay.version, REPLAY_VERSION
+                ),
+            ));
+        }
+        Ok(replay)
+    }
+}
+
+/// Where replays are saved, `None` if the platform has no data directory
+pub fn replay_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "macrophage").map(|dirs| dirs.data_dir().join("replays"))
+}
+
+/// Saved replays, newest first
+pub fn list_replays() -> Vec<PathBuf> {
+    let mut replays: Vec<PathBuf> = replay_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    replays.sort();
+    replays.reverse();
+    replays
+}
+
+/// The replay of the match currently being played, if any
+#[derive(Default)]
+pub struct ReplayRecorderRes {
+    pub recording: Option<Replay>,
+}
+
+/// Seconds each recorded phase is shown for at 1x speed
+pub const REPLAY_PHASE_SECONDS: f32 = 1.;
+
+/// A replay being watched. Inserting this drives the loaded map from the replay
+/// instead of from the game runner
+pub struct ReplayPlaybackRes {
+    pub replay: Replay,
+    /// Number of recorded phases that have been played
+    pub position: usize,
+    pub sim: Simulation,
+    pub playing: bool,
+    pub speed: f32,
+    /// First recorded phase whose checksum did not match the replayed state
+    pub desynced_at: Option<usize>,
+    elapsed: f32,
+    dirty: bool,
+}
+
+impl ReplayPlaybackRes {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlaybackRes {
+            sim: replay.simulate(0),
+            replay,
+            position: 0,
+            playing: false,
+            speed: 1.,
+            desynced_at: None,
+            elapsed: 0.,
+            dirty: true,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.replay.phases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replay.phases.is_empty()
+    }
+
+    /// Jumps to the state after `position` recorded phases
+    pub fn seek(&mut self, position: usize) {
+        let position = position.min(self.len());
+        if position == self.position {
+            return;
+        }
+
+        if position < self.position {
+            self.sim = self.replay.simulate(0);
+            self.position = 0;
+        }
+        while self.position < position {
+            self.step();
+        }
+        self.elapsed = 0.;
+    }
+
+    /// Plays the next recorded phase, returning any transmission events it produced
+    pub fn step(&mut self) -> Vec<TransmissionEvents> {
+        let recorded = match self.replay.phases.get(self.position) {
+            Some(recorded) => recorded,
+            None => return Vec::default(),
+        };
+
+        let events = self.replay.replay_phase(&mut self.sim, recorded);
+        self.position += 1;
+        self.dirty = true;
+
+        if self.desynced_at.is_none() && !self.replay.matches_recording(self.position, &self.sim) {
+            warn!(
+                "Replay diverged from the recording at phase {}",
+                self.position
+            );
+            self.desynced_at = Some(self.position);
+        }
+        events
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorderRes>();
+        app.add_system(record_replay.after(GameRunnerSystem::RunGame));
+        app.add_system(drive_replay);
+    }
+}
+
+fn drive_replay(
+    time: Res<Time>,
+    playback: Option<ResMut<ReplayPlaybackRes>>,
+    mut maps: Query<(Entity, &mut GameState)>,
+    mut transmission_events: EventWriter<TransmissionEvents>,
+    mut shown: Local<Option<Entity>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => {
+            *shown = None;
+            return;
+        }
+    };
+
+    if playback.playing {
+        playback.elapsed += time.delta_seconds() * playback.speed;
+        if playback.elapsed >= REPLAY_PHASE_SECONDS {
+            playback.elapsed = 0.;
+            let events = playback.step();
+            transmission_events.send_batch(events.into_iter());
+        }
+        if playback.position >= playback.len() {
+            playback.playing = false;
+        }
+    }
+
+    if let Ok((entity, mut map)) = maps.get_single_mut() {
+        if playback.dirty || *shown != Some(entity) {
+            *map = playback.sim.state.clone();
+            playback.dirty = false;
+            *shown = Some(entity);
+        }
+    }
+}
+
+fn record_replay(
+    mut recorder: ResMut<ReplayRecorderRes>,
+    mut accepted: EventReader<GameMoveAccepted>,
+    mut runner_events: EventReader<GameRunnerEvent>,
+    config: Option<Res<MatchConfig>>,
+    maps: Query<&GameState>,
+) {
+    for ev in accepted.iter() {
+        if let Some(phase) = recorder
+            .recording
+            .as_mut()
+            .and_then(|replay| replay.phases.last_mut())
+        {
+            phase.moves.push(RecordedMove {
+                force: ev.force,
+                game_move: ev.game_move.clone(),
+            });
+        }
+    }
+
+    for ev in runner_events.iter() {
+        match ev {
+            GameRunnerEvent::GameBegun => {
+                finish_recording(&mut recorder);
+                if let Ok(map) = maps.get_single() {
+                    recorder.recording = Some(Replay::new(
+                        config.as_ref().map(|config| (**config).clone()),
+                        map.clone(),
+                    ));
+                }
+            }
+            GameRunnerEvent::PhaseEntered { phase } => {
+                if let Some(replay) = recorder.recording.as_mut() {
+                    replay.phases.push(RecordedPhase {
+                        phase: *phase,
+                        moves: Vec::default(),
+                        checksum: None,
+                    });
+                }
+            }
+            // A stepped transmission resolves some frames after its phase was entered
+            GameRunnerEvent::TransmissionResolved => {
+                if let (Some(phase), Ok(map)) = (
+                    recorder
+                        .recording
+                        .as_mut()
+                        .and_then(|replay| replay.phases.last_mut()),
+                    maps.get_single(),
+                ) {
+                    phase.checksum = Some(StateChecksum::of(map).total);
+                }
+            }
+            GameRunnerEvent::GameCompleted { .. } => finish_recording(&mut recorder),
+            _ => {}
+        }
+    }
+
+    // The match was abandoned, keep what was played
+    if recorder.recording.is_some() && maps.is_empty() {
+        finish_recording(&mut recorder);
+    }
+}
+
+fn finish_recording(recorder: &mut ReplayRecorderRes) {
+    let mut replay = match recorder.recording.take() {
+        Some(replay) => replay,
+        None => return,
+    };
+
+    // The phase being played when recording stopped never finished
+    replay.phases.pop();
+
+    let dir = match replay_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("{}-{}.json", timestamp, replay.initial_state.name));
+
+    match replay.save(&path) {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(err) => error!("Could not save replay to {}: {}", path.display(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{Cell, NodeTenant, Occupant, PhageType, Vector};
+
+    use super::*;
+
+    #[test]
+    pub fn replays_reproduce_the_match() {
+        let mut map = GameState::new("Replay".to_string(), 1);
+        let from = map.create_node(Force(0), Vec3::ZERO);
+        let to = map.create_node(Force(0), Vec3::X);
+        map.add_vector(Vector::new(from, to)).unwrap();
+        map.nodes.get_mut(&from).unwrap().tenant = NodeTenant::Cell {
+            cell: Cell {
+                occupant: Some(Occupant(Force(0), PhageType::UV)),
+            },
+        };
+
+        let mut replay = Replay::new(None, map.clone());
+        replay.phases[0].moves.push(RecordedMove {
+            force: Force(0),
+            game_move: GameMove::MovePhage { from, to },
+        });
+        for phase in [GamePhase::InterstitialPhase, GamePhase::TransmissionPhase] {
+            replay.phases.push(RecordedPhase {
+                phase,
+                moves: Vec::default(),
+                checksum: None,
+            });
+        }
+
+        let mut sim = Simulation::new(map);
+        sim.submit(Force(0), &GameMove::MovePhage { from, to })
+            .unwrap();
+        sim.advance_round();
+        assert_eq!(replay.simulate(3), sim);
+        assert!(replay.simulate(1).state.nodes[&from].occupant().is_some());
+
+        let mut playback = ReplayPlaybackRes::new(replay.clone());
+        playback.seek(3);
+        assert_eq!(playback.sim, sim);
+        playback.seek(1);
+        assert_eq!(playback.sim, replay.simulate(1));
+        playback.step();
+        assert_eq!(playback.position, 2);
+        assert_eq!(playback.sim, replay.simulate(2));
+
+        assert_eq!(playback.desynced_at, None);
+
+        // A recording whose checksum no longer matches is reported
+        replay.phases[2].checksum = Some(StateChecksum::of(&replay.simulate(2).state).total);
+        assert!(replay.matches_recording(2, &replay.simulate(2)));
+        replay.phases[2].checksum = Some(0);
+        let mut playback = ReplayPlaybackRes::new(replay.clone());
+        playback.seek(3);
+        assert_eq!(playback.desynced_at, Some(2));
+
+        let json = serde_json::to_string(&replay).unwrap();
+        let loaded: Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, replay);
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+#[cfg(feature = "client")]
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client")]
+use super::{controller::PlayerAction, LocalPlayerRes};
+use super::{
+    gamerunner::{
+        AdvanceGamePhaseEvent, AdvanceSubPhaseEvent, GameRunnerEvent, GameRunnerRes,
+        GameRunnerSystem,
+    },
+    phasedriver::MatchSettings,
+    victory::surviving_forces,
+    Force, GameState,
+};
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 8.;
+
+/// Who is allowed to pause the match
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PausePolicy {
+    /// Anyone can pause, step and change the speed, for local play and debugging
+    #[default]
+    Free,
+
+    /// The match can not be paused or sped up
+    Disabled,
+
+    /// The match pauses or resumes once a majority of the surviving forces asked for it,
+    /// stepping and speed changes are not allowed
+    Vote,
+}
+
+/// Ways a force can control how the game runner advances
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RunnerControl {
+    TogglePause,
+    /// Advances a paused game by a single phase, or by a single
+    /// [`TransmissionSubPhase`](super::gamerunner::TransmissionSubPhase) while a
+    /// transmission phase is in progress
+    Step,
+    /// Doubles the speed, up to [`MAX_SPEED`]
+    SpeedUp,
+    /// Halves the speed, down to [`MIN_SPEED`]
+    SlowDown,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RunnerControlEvent {
+    pub force: Force,
+    pub control: RunnerControl,
+}
+
+pub struct RunnerControlPlugin;
+
+impl Plugin for RunnerControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RunnerControlEvent>();
+        #[cfg(feature = "client")]
+        app.add_system(runner_control_input);
+        app.add_system(runner_controls.before(GameRunnerSystem::RunGame));
+    }
+}
+
+#[cfg(feature = "client")]
+fn runner_control_input(
+    actions: Query<&ActionState<PlayerAction>>,
+    local_player: Option<Res<LocalPlayerRes>>,
+    mut controls: EventWriter<RunnerControlEvent>,
+) {
+    let (actions, local_player) = match (actions.get_single(), local_player) {
+        (Ok(actions), Some(local_player)) => (actions, local_player),
+        _ => return,
+    };
+
+    let bindings = [
+        (PlayerAction::TogglePause, RunnerControl::TogglePause),
+        (PlayerAction::StepPhase, RunnerControl::Step),
+        (PlayerAction::SpeedUp, RunnerControl::SpeedUp),
+        (PlayerAction::SlowDown, RunnerControl::SlowDown),
+    ];
+    for (action, control) in bindings {
+        if actions.just_pressed(action) {
+            controls.send(RunnerControlEvent {
+                force: local_player.force,
+                control,
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn runner_controls(
+    mut controls: EventReader<RunnerControlEvent>,
+    settings: Res<MatchSettings>,
+    maps: Query<&GameState>,
+    mut runner: ResMut<GameRunnerRes>,
+    mut advance: EventWriter<AdvanceGamePhaseEvent>,
+    mut advance_sub_phase: EventWriter<AdvanceSubPhaseEvent>,
+    mut runner_events: EventWriter<GameRunnerEvent>,
+    mut votes: Local<BTreeSet<Force>>,
+) {
+    for ev in controls.iter() {
+        let was_paused = runner.paused;
+        let speed = runner.speed;
+
+        match (settings.pause, ev.control) {
+            (PausePolicy::Disabled, _) => {}
+            (PausePolicy::Free, RunnerControl::TogglePause) => runner.paused = !runner.paused,
+            (PausePolicy::Free, RunnerControl::Step) => {
+                if runner.paused && runner.transmission.is_some() {
+                    advance_sub_phase.send(AdvanceSubPhaseEvent {});
+                } else if runner.paused {
+                    advance.send(AdvanceGamePhaseEvent {});
+                }
+            }
+            (PausePolicy::Free, RunnerControl::SpeedUp) => {
+                runner.speed = (runner.speed * 2.).min(MAX_SPEED)
+            }
+            (PausePolicy::Free, RunnerControl::SlowDown) => {
+                runner.speed = (runner.speed / 2.).max(MIN_SPEED)
+            }
+            (PausePolicy::Vote, RunnerControl::TogglePause) => {
+                votes.insert(ev.force);
+                let forces = maps.get_single().map(surviving_forces).unwrap_or_default();
+                if votes.intersection(&forces).count() * 2 > forces.len() {
+                    votes.clear();
+                    runner.paused = !runner.paused;
+                }
+            }
+            (PausePolicy::Vote, _) => {}
+        }
+
+        if runner.paused != was_paused {
+            runner_events.send(if runner.paused {
+                GameRunnerEvent::Paused
+            } else {
+                GameRunnerEvent::Resumed
+            });
+        }
+        if runner.speed != speed {
+            runner_events.send(GameRunnerEvent::SpeedChanged {
+                speed: runner.speed,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, prelude::Vec3};
+
+    use crate::game::{
+        gamerunner::{GameMoveEvent, GamePhase, MacroPhageGamerunnerPlugin, TransmissionEvents},
+        GameMove, Nexus, NodeId, NodeTenant, Occupant, PhageType, Vector,
+    };
+
+    use super::*;
+
+    fn runner_app(pause: PausePolicy) -> App {
+        let mut map = GameState::new("Controls".to_string(), 3);
+        for force in [Force(0), Force(1), Force(2)] {
+            let id = map.create_node(force, Vec3::ZERO);
+            map.nodes.get_mut(&id).unwrap().tenant = NodeTenant::Nexus {
+                nexus: Nexus::default(),
+            };
+        }
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(MacroPhageGamerunnerPlugin {})
+            .insert_resource(MatchSettings {
+                pause,
+                ..Default::default()
+            });
+        app.world.spawn().insert(map);
+        app.world.resource_mut::<GameRunnerRes>().run_game = true;
+        app
+    }
+
+    fn control(app: &mut App, force: u32, control: RunnerControl) {
+        app.world.send_event(RunnerControlEvent {
+            force: Force(force),
+            control,
+        });
+        app.update();
+    }
+
+    #[test]
+    pub fn paused_games_step_one_phase_at_a_time() {
+        let mut app = runner_app(PausePolicy::Free);
+
+        control(&mut app, 0, RunnerControl::Step);
+        app.update();
+        assert_eq!(
+            app.world.resource::<GameRunnerRes>().game_phase,
+            GamePhase::MutationPhase
+        );
+
+        control(&mut app, 0, RunnerControl::TogglePause);
+        assert!(app.world.resource::<GameRunnerRes>().paused);
+        control(&mut app, 0, RunnerControl::Step);
+        app.update();
+        assert_eq!(
+            app.world.resource::<GameRunnerRes>().game_phase,
+            GamePhase::InterstitialPhase
+        );
+
+        for _ in 0..5 {
+            control(&mut app, 0, RunnerControl::SpeedUp);
+        }
+        assert_eq!(app.world.resource::<GameRunnerRes>().speed, MAX_SPEED);
+        for _ in 0..5 {
+            control(&mut app, 0, RunnerControl::SlowDown);
+        }
+        assert_eq!(app.world.resource::<GameRunnerRes>().speed, MIN_SPEED);
+    }
+
+    #[test]
+    pub fn paused_transmissions_step_one_sub_phase_at_a_time() {
+        let mut app = runner_app(PausePolicy::Free);
+
+        //Force(0) and Force(1) fight over contested while Force(1) also moves into free
+        let (contested, free, from) = {
+            let mut map = app
+                .world
+                .query::<&mut GameState>()
+                .single_mut(&mut app.world);
+            let cells: Vec<NodeId> = (0..5)
+                .map(|i| map.create_node(Force::NEUTRAL, Vec3::X * i as f32))
+                .collect();
+            for pair in [(0, 1), (1, 2), (3, 4)] {
+                map.add_vector(Vector::new(cells[pair.0], cells[pair.1]))
+                    .unwrap();
+            }
+            for (cell, force, phage_type) in [
+                (cells[0], Force(0), PhageType::UV),
+                (cells[2], Force(1), PhageType::Electro),
+                (cells[4], Force(1), PhageType::Sonic),
+            ] {
+                *map.nodes
+                    .get_mut(&cell)
+                    .unwrap()
+                    .occupant_slot_mut()
+                    .unwrap() = Some(Occupant(force, phage_type));
+            }
+            (cells[1], cells[3], [cells[0], cells[2], cells[4]])
+        };
+
+        control(&mut app, 0, RunnerControl::TogglePause);
+        for (force, from, to) in [
+            (Force(0), from[0], contested),
+            (Force(1), from[1], contested),
+            (Force(1), from[2], free),
+        ] {
+            app.world.send_event(GameMoveEvent {
+                force,
+                game_move: GameMove::MovePhage { from, to },
+            });
+        }
+        control(&mut app, 0, RunnerControl::Step);
+        control(&mut app, 0, RunnerControl::Step);
+        assert_eq!(
+            app.world.resource::<GameRunnerRes>().game_phase,
+            GamePhase::TransmissionPhase
+        );
+
+        let mut reader = app
+            .world
+            .resource::<Events<TransmissionEvents>>()
+            .get_reader();
+        let mut step = |app: &mut App| -> Vec<TransmissionEvents> {
+            control(app, 0, RunnerControl::Step);
+            let events = app.world.resource::<Events<TransmissionEvents>>();
+            reader.iter(events).cloned().collect()
+        };
+        let fought = |events: &[TransmissionEvents]| {
+            events
+                .iter()
+                .any(|event| matches!(event, TransmissionEvents::CombatOccured { .. }))
+        };
+
+        //Distributing qubits and recombinating
+        step(&mut app);
+        step(&mut app);
+
+        let moved = step(&mut app);
+        assert!(moved.contains(&TransmissionEvents::PhageMoved {
+            from: from[2],
+            to: free
+        }));
+        assert!(!fought(&moved));
+        let map = app.world.query::<&GameState>().single(&app.world);
+        assert!(map.nodes[&free].occupant().is_some());
+        assert!(map.nodes[&contested].occupant().is_none());
+        assert_eq!(map.transmission_phases, 0);
+
+        assert!(fought(&step(&mut app)));
+        step(&mut app);
+        let runner = app.world.resource::<GameRunnerRes>();
+        assert!(runner.transmission.is_none());
+        assert_eq!(runner.game_phase, GamePhase::TransmissionPhase);
+        let map = app.world.query::<&GameState>().single(&app.world);
+        assert_eq!(map.transmission_phases, 1);
+    }
+
+    #[test]
+    pub fn networked_games_vote_to_pause() {
+        let mut app = runner_app(PausePolicy::Vote);
+
+        control(&mut app, 0, RunnerControl::TogglePause);
+        control(&mut app, 0, RunnerControl::SpeedUp);
+        assert!(!app.world.resource::<GameRunnerRes>().paused);
+        assert_eq!(app.world.resource::<GameRunnerRes>().speed, 1.);
+
+        control(&mut app, 2, RunnerControl::TogglePause);
+        assert!(app.world.resource::<GameRunnerRes>().paused);
+
+        let mut app = runner_app(PausePolicy::Disabled);
+        control(&mut app, 0, RunnerControl::TogglePause);
+        assert!(!app.world.resource::<GameRunnerRes>().paused);
+    }
+}
@@ -74,6 +74,14 @@ impl Default for GameSettings {
                 (KeyCode::Key2, PlayerAction::HotKey2),
                 (KeyCode::Key3, PlayerAction::HotKey3),
                 (KeyCode::Key4, PlayerAction::HotKey4),
+                (KeyCode::T, PlayerAction::OpenQubitTradePanel),
+                (KeyCode::Return, PlayerAction::EndTurn),
+                (KeyCode::P, PlayerAction::TogglePause),
+                (KeyCode::N, PlayerAction::StepPhase),
+                (KeyCode::Equals, PlayerAction::SpeedUp),
+                (KeyCode::Minus, PlayerAction::SlowDown),
+                (KeyCode::Z, PlayerAction::Undo),
+                (KeyCode::Y, PlayerAction::Redo),
                 (KeyCode::PageUp, PlayerAction::ZoomIn),
                 (KeyCode::PageDown, PlayerAction::ZoomOut),
                 (KeyCode::Left, PlayerAction::PanLeft),
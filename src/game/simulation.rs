@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gamerunner::{GamePhase, TransmissionEvents},
+    trade::{accept_qubits, offer_qubits},
+    transmission::{transmit, MoveOrder, PendingOrders},
+    validation::validate_mutation,
+    victory::victor,
+    Force, GameMove, GameState, PlayerActionError, Vector,
+};
+
+/// Plays a match without any bevy systems, windows or assets. The game runner plugin
+/// drives the same functions from events, this drives them directly for tests and tools
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Simulation {
+    pub state: GameState,
+    pub phase: GamePhase,
+    pub orders: PendingOrders,
+}
+
+impl Simulation {
+    /// Starts a simulation at the beginning of the first mutation phase
+    pub fn new(state: GameState) -> Self {
+        Simulation {
+            state,
+            phase: GamePhase::MutationPhase,
+            orders: PendingOrders::default(),
+        }
+    }
+
+    pub fn submit(&mut self, force: Force, game_move: &GameMove) -> Result<(), PlayerActionError> {
+        submit_move(
+            &mut self.state,
+            &mut self.orders,
+            self.phase,
+            force,
+            game_move,
+        )
+    }
+
+    /// Advances to the next phase, returning what happened if that was a transmission phase
+    pub fn advance(&mut self) -> Vec<TransmissionEvents> {
+        self.phase = self.phase.next();
+        if self.phase == GamePhase::TransmissionPhase {
+            resolve_transmission(&mut self.state, &mut self.orders)
+        } else {
+            Vec::default()
+        }
+    }
+
+    /// Runs phases until the start of the next mutation phase
+    pub fn advance_round(&mut self) -> Vec<TransmissionEvents> {
+        let mut events = self.advance();
+        while self.phase != GamePhase::MutationPhase {
+            events.extend(self.advance());
+        }
+        events
+    }
+
+    pub fn victor(&self) -> Option<Force> {
+        victor(&self.state)
+    }
+}
+
+/// Applies a move submitted by `force` during `phase`. Mutations take effect immediately,
+/// phage movement is queued in `orders` until the next transmission phase
+pub fn submit_move(
+    state: &mut GameState,
+    orders: &mut PendingOrders,
+    phase: GamePhase,
+    force: Force,
+    game_move: &GameMove,
+) -> Result<(), PlayerActionError> {
+    match game_move {
+        GameMove::MovePhage { from, to } => {
+            if phase != GamePhase::MutationPhase {
+                return Err(PlayerActionError::WrongPhase(phase));
+            }
+
+            let node = state
+                .nodes
+                .get(from)
+                .ok_or(PlayerActionError::NodeIdDoesNotExist(*from))?;
+            if node.occupant().map(|occupant| occupant.0) != Some(force) {
+                return Err(PlayerActionError::NotControlledBy { node: *from, force });
+            }
+
+            let vector = Vector::new(*from, *to);
+            if !state.vector_exists(vector) {
+                return Err(PlayerActionError::VectorDoesNotExist { vector });
+            }
+
+            orders.queue_move(MoveOrder {
+                force,
+                from: *from,
+                to: *to,
+            });
+            Ok(())
+        }
+        GameMove::Mutate { mutation } => {
+            validate_mutation(state, force, mutation, phase)?;
+            state.apply_mutation(force, mutation)
+        }
+        GameMove::GiveQubits { source, dest, qty } => {
+            if phase != GamePhase::MutationPhase {
+                return Err(PlayerActionError::WrongPhase(phase));
+            }
+            offer_qubits(state, orders, force, *source, *dest, *qty)
+        }
+        GameMove::AcceptQubits { source } => {
+            if phase == GamePhase::TransmissionPhase {
+                return Err(PlayerActionError::WrongPhase(phase));
+            }
+            accept_qubits(orders, force, *source)
+        }
+    }
+}
+
+/// Runs the transmission phase against `state` and clears the orders it consumed
+pub fn resolve_transmission(
+    state: &mut GameState,
+    orders: &mut PendingOrders,
+) -> Vec<TransmissionEvents> {
+    let (next_state, events) = transmit(state, orders);
+    *state = next_state;
+    *orders = PendingOrders::default();
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{Cell, NodeId, NodeTenant, Occupant, PhageType};
+
+    use super::*;
+
+    #[test]
+    pub fn simulation_plays_scripted_moves() {
+        let mut map = GameState::new("Pair".to_string(), 1);
+        let from = map.create_node(Force(0), Vec3::ZERO);
+        let to = map.create_node(Force(0), Vec3::X);
+        map.add_vector(Vector::new(from, to)).unwrap();
+        map.nodes.get_mut(&from).unwrap().tenant = NodeTenant::Cell {
+            cell: Cell {
+                occupant: Some(Occupant(Force(0), PhageType::UV)),
+            },
+        };
+
+        let mut sim = Simulation::new(map);
+        assert!(matches!(
+            sim.submit(Force(1), &GameMove::MovePhage { from, to }),
+            Err(PlayerActionError::NotControlledBy { .. })
+        ));
+        sim.submit(Force(0), &GameMove::MovePhage { from, to })
+            .unwrap();
+
+        let events = sim.advance_round();
+        assert_eq!(events, vec![TransmissionEvents::PhageMoved { from, to }]);
+        assert_eq!(sim.phase, GamePhase::MutationPhase);
+        assert!(sim.orders.moves.is_empty());
+        assert!(sim.state.nodes[&NodeId(1)].occupant().is_some());
+
+        sim.advance();
+        assert!(matches!(
+            sim.submit(Force(0), &GameMove::MovePhage { from: to, to: from }),
+            Err(PlayerActionError::WrongPhase(GamePhase::InterstitialPhase))
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gamerunner::TransmissionEvents, transmission::PendingOrders, Force, GameState,
+    PlayerActionError,
+};
+
+/// Per map rules for giving qubits to other forces
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(default)]
+pub struct TradeRules {
+    /// Offers only settle once the receiving force has accepted them
+    pub require_acceptance: bool,
+
+    /// Forces allowed to trade with each other. When empty any force may trade with any other
+    pub alliances: Vec<BTreeSet<Force>>,
+}
+
+impl TradeRules {
+    pub fn allied(&self, a: Force, b: Force) -> bool {
+        self.alliances.is_empty()
+            || self
+                .alliances
+                .iter()
+                .any(|alliance| alliance.contains(&a) && alliance.contains(&b))
+    }
+}
+
+/// Qubits a force has offered to another, settled at the start of the next transmission phase
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TradeOffer {
+    pub source: Force,
+    pub dest: Force,
+    pub qty: u32,
+    pub accepted: bool,
+}
+
+/// Queues qubits from `source` for `dest`. Every outstanding offer from `source` has to be
+/// covered by its current balance
+pub fn offer_qubits(
+    state: &GameState,
+    orders: &mut PendingOrders,
+    force: Force,
+    source: Force,
+    dest: Force,
+    qty: u32,
+) -> Result<(), PlayerActionError> {
+    if source != force {
+        return Err(PlayerActionError::NotYourQubits(source));
+    }
+
+    if source == dest || qty == 0 || !state.trade_rules.allied(source, dest) {
+        return Err(PlayerActionError::InvalidTrade { source, dest });
+    }
+
+    let offered: u32 = orders
+        .trades
+        .iter()
+        .filter(|offer| offer.source == source)
+        .map(|offer| offer.qty)
+        .sum();
+    let available = state.qubits(source).saturating_sub(offered);
+    if available < qty {
+        return Err(PlayerActionError::InsufficientQubits {
+            cost: qty,
+            available,
+        });
+    }
+
+    orders.trades.push(TradeOffer {
+        source,
+        dest,
+        qty,
+        accepted: !state.trade_rules.require_acceptance,
+    });
+    Ok(())
+}
+
+/// Accepts every outstanding offer `source` made to `force`
+pub fn accept_qubits(
+    orders: &mut PendingOrders,
+    force: Force,
+    source: Force,
+) -> Result<(), PlayerActionError> {
+    let mut found = false;
+    for offer in orders
+        .trades
+        .iter_mut()
+        .filter(|offer| offer.source == source && offer.dest == force)
+    {
+        offer.accepted = true;
+        found = true;
+    }
+
+    if found {
+        Ok(())
+    } else {
+        Err(PlayerActionError::NoTradeOffered { source })
+    }
+}
+
+/// Transfers every accepted offer in the order it was made. Offers the source can no longer
+/// afford, or the receiver never accepted, are dropped
+pub(crate) fn settle_trades(
+    state: &mut GameState,
+    orders: &PendingOrders,
+    events: &mut Vec<TransmissionEvents>,
+) {
+    for offer in orders.trades.iter().filter(|offer| offer.accepted) {
+        if state.spend_qubits(offer.source, offer.qty).is_ok() {
+            state.grant_qubits(offer.dest, offer.qty);
+            events.push(TransmissionEvents::QubitsTraded {
+                source: offer.source,
+                dest: offer.dest,
+                qty: offer.qty,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::transmission::transmit;
+
+    use super::*;
+
+    #[test]
+    pub fn accepted_trades_settle_during_transmission() {
+        let mut map = GameState::new("Trade".to_string(), 2);
+        map.trade_rules.require_acceptance = true;
+        map.grant_qubits(Force(0), 10);
+        let mut orders = PendingOrders::default();
+
+        assert!(matches!(
+            offer_qubits(&map, &mut orders, Force(1), Force(0), Force(1), 5),
+            Err(PlayerActionError::NotYourQubits(Force(0)))
+        ));
+        offer_qubits(&map, &mut orders, Force(0), Force(0), Force(1), 6).unwrap();
+        assert!(matches!(
+            offer_qubits(&map, &mut orders, Force(0), Force(0), Force(1), 6),
+            Err(PlayerActionError::InsufficientQubits {
+                cost: 6,
+                available: 4
+            })
+        ));
+
+        let (unaccepted, events) = transmit(&map, &orders);
+        assert!(events.is_empty());
+        assert_eq!(unaccepted.qubits(Force(0)), 10);
+
+        accept_qubits(&mut orders, Force(1), Force(0)).unwrap();
+        let (state, events) = transmit(&map, &orders);
+        assert_eq!(
+            events,
+            vec![TransmissionEvents::QubitsTraded {
+                source: Force(0),
+                dest: Force(1),
+                qty: 6
+            }]
+        );
+        assert_eq!(state.qubits(Force(0)), 4);
+        assert_eq!(state.qubits(Force(1)), 6);
+    }
+
+    #[test]
+    pub fn only_allies_trade() {
+        let mut map = GameState::new("Trade".to_string(), 3);
+        map.trade_rules.alliances = vec![BTreeSet::from([Force(0), Force(1)])];
+        map.grant_qubits(Force(0), 10);
+        let mut orders = PendingOrders::default();
+
+        assert!(matches!(
+            offer_qubits(&map, &mut orders, Force(0), Force(0), Force(2), 5),
+            Err(PlayerActionError::InvalidTrade { .. })
+        ));
+        offer_qubits(&map, &mut orders, Force(0), Force(0), Force(1), 5).unwrap();
+        assert!(orders.trades[0].accepted);
+    }
+}
@@ -0,0 +1,1197 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gamerunner::{TransmissionEvents, TransmissionSubPhase},
+    recombinator::recombinate,
+    trade::{settle_trades, TradeOffer},
+    victory::surviving_forces,
+    Force, GameState, Nexus, NodeId, NodeTenant, Occupant, Vector,
+};
+
+/// A force's request to move the phage occupying `from` into `to` along an existing vector
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct MoveOrder {
+    pub force: Force,
+    pub from: NodeId,
+    pub to: NodeId,
+}
+
+/// Everything players submitted during the mutation phase that is resolved
+/// by the next transmission phase
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct PendingOrders {
+    pub moves: Vec<MoveOrder>,
+
+    /// Qubit offers, in the order they were made
+    #[serde(default)]
+    pub trades: Vec<TradeOffer>,
+}
+
+impl PendingOrders {
+    /// Queues a move, replacing any move already queued for the same phage
+    pub fn queue_move(&mut self, order: MoveOrder) {
+        self.moves.retain(|queued| queued.from != order.from);
+        self.moves.push(order);
+    }
+}
+
+/// A cell that phage from opposing forces attempted to occupy during the movement
+/// sub phase. The attackers stay in the cells they came from until combat resolves it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Contest {
+    pub cell: NodeId,
+
+    /// The cell each attacking phage is moving from, along with the phage itself
+    pub attackers: Vec<(NodeId, Occupant)>,
+}
+
+/// Runs a single transmission phase, settling accepted qubit trades and then executing
+/// every [`TransmissionSubPhase`] in order.
+/// This must stay a pure function: the same state and orders always produce the same
+/// resulting state and events, on every client.
+pub fn transmit(state: &GameState, orders: &PendingOrders) -> (GameState, Vec<TransmissionEvents>) {
+    Transmission::begin(state, orders).finish()
+}
+
+/// A transmission phase in progress, so it can be run one [`TransmissionSubPhase`] at a
+/// time. Running every sub phase always ends in the same state as [`transmit`]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Transmission {
+    state: GameState,
+    orders: PendingOrders,
+    /// Index into [`TransmissionSubPhase::ORDER`] of the next sub phase to run
+    cursor: usize,
+    contests: Vec<Contest>,
+    advantaged: BTreeSet<NodeId>,
+    events: Vec<TransmissionEvents>,
+    /// How many of `events` have already been handed out by [`Transmission::step`]
+    reported: usize,
+}
+
+impl Transmission {
+    /// Settles accepted qubit trades, no sub phase has run yet
+    pub fn begin(state: &GameState, orders: &PendingOrders) -> Self {
+        let mut transmission = Transmission {
+            state: state.clone(),
+            orders: orders.clone(),
+            cursor: 0,
+            contests: Vec::default(),
+            advantaged: BTreeSet::new(),
+            events: Vec::default(),
+            reported: 0,
+        };
+        settle_trades(&mut transmission.state, orders, &mut transmission.events);
+        transmission
+    }
+
+    /// The state as the sub phases run so far have left it
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The sub phase the next [`Transmission::step`] runs, `None` once all of them ran
+    pub fn next_sub_phase(&self) -> Option<TransmissionSubPhase> {
+        TransmissionSubPhase::ORDER.get(self.cursor).copied()
+    }
+
+    /// Runs the next sub phase, returning the events it produced along with any from
+    /// settling trades that were not handed out yet
+    pub fn step(&mut self) -> Vec<TransmissionEvents> {
+        let sub_phase = match self.next_sub_phase() {
+            Some(sub_phase) => sub_phase,
+            None => return Vec::default(),
+        };
+        self.cursor += 1;
+
+        let (next, events) = (&mut self.state, &mut self.events);
+        match sub_phase {
+            TransmissionSubPhase::DistributeQubits => distribute_qubits(next, events),
+            TransmissionSubPhase::ReCombinate => {
+                self.contests = recombinate(next, &mut self.advantaged, events)
+            }
+            TransmissionSubPhase::Movement => {
+                self.contests.extend(movement(next, &self.orders, events))
+            }
+            TransmissionSubPhase::Combat => combat(
+                next,
+                &merge_contests(self.contests.drain(..)),
+                &self.advantaged,
+                events,
+            ),
+            TransmissionSubPhase::Replicate => replicate(next, events),
+        }
+
+        let fresh = self.events[self.reported..].to_vec();
+        self.reported = self.events.len();
+        fresh
+    }
+
+    /// Runs the remaining sub phases and wraps the phase up, returning the resulting
+    /// state and every event of the phase, including those already stepped through
+    pub fn finish(mut self) -> (GameState, Vec<TransmissionEvents>) {
+        while self.next_sub_phase().is_some() {
+            self.step();
+        }
+
+        observe(&mut self.state, &self.events);
+        self.state.transmission_phases += 1;
+        self.state.mutated_tiles.clear();
+        (self.state, self.events)
+    }
+}
+
+/// Pays out every occupied generator whose cadence lines up with the phase being run to
+/// the force occupying it, whoever owns the generator node. Unoccupied generators pay
+/// nobody
+fn distribute_qubits(state: &mut GameState, events: &mut Vec<TransmissionEvents>) {
+    let phase = state.transmission_phases + 1;
+
+    for id in state.node_ids_in_order() {
+        let node = &state.nodes[&id];
+        if let NodeTenant::Generator { generator } = &node.tenant {
+            let recipient = match generator.occupant {
+                Some(Occupant(force, _)) => force,
+                None => continue,
+            };
+            if !phase.is_multiple_of(generator.speed.max(1)) {
+                continue;
+            }
+
+            let qty = generator.amt;
+            state.grant_qubits(recipient, qty);
+            events.push(TransmissionEvents::QubitsDistributed {
+                recipient,
+                qty,
+                source: id,
+            });
+        }
+    }
+}
+
+/// Resolves every valid move order at once:
+/// * Moves into free cells, or cells whose occupant is leaving, succeed
+/// * Phage from opposing forces moving into the same cell, or into a cell held by an
+///   opposing phage that is staying put, contest that cell and are left for combat
+/// * When allied phage move into the same cell the one farthest from its nexus moves
+///   and the others stay put
+/// * Opposing phage trying to swap cells along the same vector block each other
+///
+/// Returns the contested cells in ascending order
+fn movement(
+    state: &mut GameState,
+    orders: &PendingOrders,
+    events: &mut Vec<TransmissionEvents>,
+) -> Vec<Contest> {
+    let mut orders = orders.moves.clone();
+    orders.sort();
+
+    //Only the first valid order for each phage is considered
+    let mut moving: BTreeMap<NodeId, (NodeId, Occupant)> = BTreeMap::new();
+    for order in orders {
+        if moving.contains_key(&order.from) || !is_valid_move(state, &order) {
+            continue;
+        }
+        let phage = *state.nodes[&order.from].occupant().unwrap();
+        moving.insert(order.from, (order.to, phage));
+    }
+
+    let mut contests: BTreeMap<NodeId, Vec<(NodeId, Occupant)>> = BTreeMap::new();
+    let mut hops: BTreeMap<Force, BTreeMap<NodeId, u32>> = BTreeMap::new();
+
+    //Halting one move can block the moves behind it, so repeat until nothing changes
+    loop {
+        let mut by_dest: BTreeMap<NodeId, Vec<(NodeId, Occupant)>> = BTreeMap::new();
+        for (from, (to, phage)) in moving.iter() {
+            by_dest.entry(*to).or_default().push((*from, *phage));
+        }
+
+        let mut halted = Vec::default();
+        for (dest, movers) in by_dest {
+            //Phage can never enter a nexus, moving into one lays siege to it during combat
+            let force = movers[0].1 .0;
+            let is_nexus = matches!(state.nodes[&dest].tenant, NodeTenant::Nexus { .. });
+            if is_nexus || movers.iter().any(|(_, phage)| phage.0 != force) {
+                halted.extend(movers.iter().map(|(from, _)| *from));
+                contests.entry(dest).or_default().extend(movers);
+                continue;
+            }
+
+            let mover = if movers.len() > 1 {
+                let hops = hops
+                    .entry(force)
+                    .or_insert_with(|| hops_from_nexus(state, force));
+                let mover = *movers
+                    .iter()
+                    .max_by_key(|(from, _)| {
+                        (hops.get(from).copied().unwrap_or(u32::MAX), Reverse(*from))
+                    })
+                    .unwrap();
+                halted.extend(
+                    movers
+                        .iter()
+                        .filter(|(from, _)| *from != mover.0)
+                        .map(|(from, _)| *from),
+                );
+                mover
+            } else {
+                movers[0]
+            };
+
+            if let Some(defender) = state.nodes[&dest].occupant() {
+                match moving.get(&dest) {
+                    Some((back, _)) if *back == mover.0 && defender.0 != force => {
+                        halted.push(mover.0);
+                        halted.push(dest);
+                    }
+                    Some(_) => {}
+                    None if defender.0 == force => halted.push(mover.0),
+                    None => {
+                        halted.push(mover.0);
+                        contests.entry(dest).or_default().push(mover);
+                    }
+                }
+            }
+        }
+
+        if halted.is_empty() {
+            break;
+        }
+        for from in halted {
+            moving.remove(&from);
+        }
+    }
+
+    //Every phage leaves before any arrive so chains and allied swaps resolve cleanly
+    for (from, (to, _)) in moving.iter() {
+        if let Some(slot) = state.nodes.get_mut(from).unwrap().occupant_slot_mut() {
+            *slot = None;
+        }
+        events.push(TransmissionEvents::PhageMoved {
+            from: *from,
+            to: *to,
+        });
+    }
+    for (to, phage) in moving.values() {
+        if let Some(slot) = state.nodes.get_mut(to).unwrap().occupant_slot_mut() {
+            *slot = Some(*phage);
+        }
+    }
+
+    contests
+        .into_iter()
+        .map(|(cell, attackers)| Contest { cell, attackers })
+        .collect()
+}
+
+/// A move is valid when it follows an existing vector, the moving force occupies
+/// the source cell and the destination can hold phage or is an opposing nexus
+fn is_valid_move(state: &GameState, order: &MoveOrder) -> bool {
+    if order.from == order.to || !state.vector_exists(Vector::new(order.from, order.to)) {
+        return false;
+    }
+
+    let owns_source = matches!(
+        state.nodes.get(&order.from).and_then(|node| node.occupant()),
+        Some(occupant) if occupant.0 == order.force
+    );
+    let can_enter = match state.nodes.get(&order.to) {
+        Some(node) if matches!(node.tenant, NodeTenant::Nexus { .. }) => node.force != order.force,
+        Some(node) => node.can_be_occupied(),
+        None => false,
+    };
+
+    owns_source && can_enter
+}
+
+/// Number of vector hops from every reachable node to the closest nexus owned by `force`
+fn hops_from_nexus(state: &GameState, force: Force) -> BTreeMap<NodeId, u32> {
+    state.hops_from(
+        state
+            .nodes
+            .values()
+            .filter(|node| node.force == force && matches!(node.tenant, NodeTenant::Nexus { .. }))
+            .map(|node| node.id),
+    )
+}
+
+/// Combines contests for the same cell, attackers are kept in the order they were added
+fn merge_contests(contests: impl Iterator<Item = Contest>) -> Vec<Contest> {
+    let mut merged: BTreeMap<NodeId, Vec<(NodeId, Occupant)>> = BTreeMap::new();
+    for contest in contests {
+        let attackers = merged.entry(contest.cell).or_default();
+        for attacker in contest.attackers {
+            if !attackers.contains(&attacker) {
+                attackers.push(attacker);
+            }
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(cell, attackers)| Contest { cell, attackers })
+        .collect()
+}
+
+/// Resolves every contested cell in ascending cell order. The current occupant holds the
+/// cell first, then attackers challenge it in order of the cell they came from. A challenger
+/// only takes over when the map's [`MatchupTable`](super::combat::MatchupTable) says it
+/// beats the holder, so ties always favour whoever holds the cell. A defender with a
+/// recombinator's advantage can't be beaten at all. Losers are destroyed and the final
+/// holder occupies the cell
+fn combat(
+    state: &mut GameState,
+    contests: &[Contest],
+    advantaged: &BTreeSet<NodeId>,
+    events: &mut Vec<TransmissionEvents>,
+) {
+    for contest in contests {
+        //Earlier combat may already have destroyed or moved an attacker
+        let attackers: Vec<(NodeId, Occupant)> = contest
+            .attackers
+            .iter()
+            .filter(|(from, phage)| state.nodes[from].occupant() == Some(phage))
+            .copied()
+            .collect();
+
+        if matches!(state.nodes[&contest.cell].tenant, NodeTenant::Nexus { .. }) {
+            siege(state, contest.cell, &attackers, events);
+            continue;
+        }
+
+        //The cell the holder attacked from, None when it is defending the cell
+        let mut holder: Option<(Option<NodeId>, Occupant)> = state.nodes[&contest.cell]
+            .occupant()
+            .map(|defender| (None, *defender));
+
+        for (from, challenger) in attackers {
+            let (held_from, held) = match holder {
+                None => {
+                    holder = Some((Some(from), challenger));
+                    continue;
+                }
+                //Allies don't fight, the challenger stays where it is
+                Some((_, held)) if held.0 == challenger.0 => continue,
+                Some(held) => held,
+            };
+
+            let defended = held_from.is_none() && advantaged.contains(&contest.cell);
+            let (victor, loser, loser_cell) =
+                if !defended && state.matchups.beats(challenger.1, held.1) {
+                    holder = Some((Some(from), challenger));
+                    (challenger, held, held_from.unwrap_or(contest.cell))
+                } else {
+                    (held, challenger, from)
+                };
+
+            if let Some(slot) = state
+                .nodes
+                .get_mut(&loser_cell)
+                .unwrap()
+                .occupant_slot_mut()
+            {
+                *slot = None;
+            }
+            events.push(TransmissionEvents::CombatOccured {
+                cell: contest.cell,
+                victor: (victor.0, victor.1),
+                lose: (loser.0, loser.1),
+            });
+        }
+
+        if let Some((Some(from), victor)) = holder {
+            if let Some(slot) = state.nodes.get_mut(&from).unwrap().occupant_slot_mut() {
+                *slot = None;
+            }
+            if let Some(slot) = state
+                .nodes
+                .get_mut(&contest.cell)
+                .unwrap()
+                .occupant_slot_mut()
+            {
+                *slot = Some(victor);
+            }
+            events.push(TransmissionEvents::PhageMoved {
+                from,
+                to: contest.cell,
+            });
+        }
+    }
+}
+
+/// Every opposing phage attacking a nexus is consumed and deals one point of damage. A nexus
+/// that runs out of health is captured by the force that dealt the final blow, and a force
+/// left without any nexus is eliminated
+fn siege(
+    state: &mut GameState,
+    nexus: NodeId,
+    attackers: &[(NodeId, Occupant)],
+    events: &mut Vec<TransmissionEvents>,
+) {
+    for (from, attacker) in attackers {
+        let owner = state.nodes[&nexus].force;
+        if attacker.0 == owner {
+            continue;
+        }
+
+        *state
+            .nodes
+            .get_mut(from)
+            .unwrap()
+            .occupant_slot_mut()
+            .unwrap() = None;
+
+        let node = state.nodes.get_mut(&nexus).unwrap();
+        let health = match &mut node.tenant {
+            NodeTenant::Nexus { nexus } => {
+                nexus.health = nexus.health.saturating_sub(1);
+                if nexus.health == 0 {
+                    nexus.health = Nexus::default().health;
+                    node.force = attacker.0;
+                }
+                nexus.health
+            }
+            _ => return,
+        };
+
+        events.push(TransmissionEvents::NexusDamaged {
+            nexus,
+            attacker: (attacker.0, attacker.1),
+            health,
+        });
+
+        if node.force != owner {
+            events.push(TransmissionEvents::NexusCaptured {
+                nexus,
+                from: owner,
+                by: attacker.0,
+            });
+
+            if !owner.is_neutral() && !surviving_forces(state).contains(&owner) {
+                state.eliminated.insert(owner);
+                events.push(TransmissionEvents::ForceEliminated { force: owner });
+            }
+        }
+    }
+}
+
+/// Advances every replicator's counter, replicators that are due spawn a phage for the
+/// force that owns them. A replicator with no free neighbor holds its phage until one frees up
+fn replicate(state: &mut GameState, events: &mut Vec<TransmissionEvents>) {
+    for id in state.node_ids_in_order() {
+        let node = state.nodes.get_mut(&id).unwrap();
+        let force = node.force;
+        let (output, due) = match &mut node.tenant {
+            NodeTenant::Replicator { replicator } => {
+                replicator.phases_waited = (replicator.phases_waited + 1).min(replicator.speed);
+                (
+                    replicator.output,
+                    replicator.phases_waited >= replicator.speed,
+                )
+            }
+            _ => continue,
+        };
+        if !due {
+            continue;
+        }
+
+        let free = state.get_all_neighbors(id).into_iter().find(|neighbor| {
+            let node = &state.nodes[neighbor];
+            node.can_be_occupied() && node.occupant().is_none()
+        });
+
+        if let Some(cell) = free {
+            let phage = Occupant(force, output);
+            *state
+                .nodes
+                .get_mut(&cell)
+                .unwrap()
+                .occupant_slot_mut()
+                .unwrap() = Some(phage);
+            if let NodeTenant::Replicator { replicator } =
+                &mut state.nodes.get_mut(&id).unwrap().tenant
+            {
+                replicator.phases_waited = 0;
+            }
+            events.push(TransmissionEvents::PhageReplicated {
+                replicator: id,
+                cell,
+                phage: (force, output),
+            });
+        }
+    }
+}
+
+/// Lets recombinators know about phage passing through and fighting over them so
+/// their triggers can be evaluated during the next ReCombinate sub phase
+fn observe(state: &mut GameState, events: &[TransmissionEvents]) {
+    for event in events {
+        match event {
+            TransmissionEvents::PhageMoved { from, to } => {
+                if let Some(observed) = state.recombinator_observations_mut(*from) {
+                    observed.phage_exited = true;
+                }
+                if let Some(observed) = state.recombinator_observations_mut(*to) {
+                    observed.phage_entered = true;
+                }
+            }
+            TransmissionEvents::CombatOccured { cell, .. } => {
+                if let Some(observed) = state.recombinator_observations_mut(*cell) {
+                    observed.combat_occured = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{
+        Generator, Mutation, PhageType, PlayerActionError, Recombinator, RecombinatorEffect,
+        RecombinatorObservations, RecombinatorTriggers,
+    };
+
+    use super::*;
+
+    fn line_map() -> GameState {
+        let mut map = GameState::new("Line".to_string(), 2);
+
+        let mut previous = None;
+        for i in 0..5 {
+            let id = map.create_node(Force(0), Vec3::new(i as f32, 0., 0.));
+            if let Some(previous) = previous {
+                map.add_vector(Vector::new(previous, id)).unwrap();
+            }
+            previous = Some(id);
+        }
+
+        place(&mut map, 0, Force(0), PhageType::UV);
+        place(&mut map, 4, Force(1), PhageType::Sonic);
+
+        map
+    }
+
+    fn place(map: &mut GameState, id: u32, force: Force, phage_type: PhageType) {
+        *map.nodes
+            .get_mut(&NodeId(id))
+            .unwrap()
+            .occupant_slot_mut()
+            .unwrap() = Some(Occupant(force, phage_type));
+    }
+
+    fn recombinator(
+        map: &mut GameState,
+        id: u32,
+        trigger: RecombinatorTriggers,
+        effect: RecombinatorEffect,
+    ) {
+        map.nodes.get_mut(&NodeId(id)).unwrap().tenant = NodeTenant::Recombinator {
+            recombinator: Recombinator {
+                trigger,
+                effect,
+                occupant: None,
+                observed: RecombinatorObservations::default(),
+            },
+        };
+    }
+
+    fn order(force: u32, from: u32, to: u32) -> MoveOrder {
+        MoveOrder {
+            force: Force(force),
+            from: NodeId(from),
+            to: NodeId(to),
+        }
+    }
+
+    fn orders() -> PendingOrders {
+        PendingOrders {
+            moves: vec![
+                MoveOrder {
+                    force: Force(1),
+                    from: NodeId(4),
+                    to: NodeId(3),
+                },
+                MoveOrder {
+                    force: Force(0),
+                    from: NodeId(0),
+                    to: NodeId(1),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn transmit_is_deterministic() {
+        let map = line_map();
+        let orders = orders();
+
+        let first = transmit(&map, &orders);
+        let second = transmit(&map, &orders);
+
+        assert_eq!(first, second);
+        assert_eq!(first.0.transmission_phases, 1);
+    }
+
+    #[test]
+    pub fn transmit_moves_phage_along_vectors() {
+        let (state, events) = transmit(&line_map(), &orders());
+
+        assert!(state.nodes[&NodeId(0)].occupant().is_none());
+        assert_eq!(
+            state.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(0), PhageType::UV))
+        );
+        assert_eq!(
+            events,
+            vec![
+                TransmissionEvents::PhageMoved {
+                    from: NodeId(0),
+                    to: NodeId(1)
+                },
+                TransmissionEvents::PhageMoved {
+                    from: NodeId(4),
+                    to: NodeId(3)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn transmit_ignores_moves_without_vector() {
+        let orders = PendingOrders {
+            moves: vec![MoveOrder {
+                force: Force(0),
+                from: NodeId(0),
+                to: NodeId(2),
+            }],
+            ..Default::default()
+        };
+
+        let (state, events) = transmit(&line_map(), &orders);
+
+        assert!(events.is_empty());
+        assert!(state.nodes[&NodeId(0)].occupant().is_some());
+    }
+
+    #[test]
+    pub fn opposing_moves_into_the_same_cell_are_contested() {
+        let mut map = line_map();
+        place(&mut map, 2, Force(1), PhageType::Sonic);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1), order(1, 2, 1)],
+            ..Default::default()
+        };
+
+        let mut events = Vec::default();
+        let contests = movement(&mut map, &orders, &mut events);
+
+        assert!(events.is_empty());
+        assert_eq!(
+            contests,
+            vec![Contest {
+                cell: NodeId(1),
+                attackers: vec![
+                    (NodeId(0), Occupant(Force(0), PhageType::UV)),
+                    (NodeId(2), Occupant(Force(1), PhageType::Sonic)),
+                ],
+            }]
+        );
+        assert!(map.nodes[&NodeId(0)].occupant().is_some());
+        assert!(map.nodes[&NodeId(2)].occupant().is_some());
+    }
+
+    #[test]
+    pub fn moving_into_a_held_enemy_cell_is_contested() {
+        let mut map = line_map();
+        place(&mut map, 1, Force(1), PhageType::Sonic);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1)],
+            ..Default::default()
+        };
+
+        let mut events = Vec::default();
+        let contests = movement(&mut map, &orders, &mut events);
+
+        assert!(events.is_empty());
+        assert_eq!(contests.len(), 1);
+        assert_eq!(contests[0].cell, NodeId(1));
+    }
+
+    #[test]
+    pub fn allied_collisions_move_the_phage_farthest_from_the_nexus() {
+        let mut map = line_map();
+        map.nodes.get_mut(&NodeId(0)).unwrap().tenant = NodeTenant::Nexus {
+            nexus: Nexus::default(),
+        };
+        map.add_vector(Vector::new(NodeId(1), NodeId(3))).unwrap();
+        place(&mut map, 1, Force(0), PhageType::UV);
+        place(&mut map, 2, Force(0), PhageType::Electro);
+        let orders = PendingOrders {
+            moves: vec![order(0, 1, 3), order(0, 2, 3)],
+            ..Default::default()
+        };
+
+        let mut events = Vec::default();
+        let contests = movement(&mut map, &orders, &mut events);
+
+        assert!(contests.is_empty());
+        assert_eq!(
+            events,
+            vec![TransmissionEvents::PhageMoved {
+                from: NodeId(2),
+                to: NodeId(3)
+            }]
+        );
+        assert!(map.nodes[&NodeId(1)].occupant().is_some());
+    }
+
+    #[test]
+    pub fn phage_can_follow_allies_out_of_a_cell() {
+        let mut map = line_map();
+        place(&mut map, 1, Force(0), PhageType::Electro);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1), order(0, 1, 2)],
+            ..Default::default()
+        };
+
+        let mut events = Vec::default();
+        movement(&mut map, &orders, &mut events);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            map.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(0), PhageType::UV))
+        );
+        assert_eq!(
+            map.nodes[&NodeId(2)].occupant(),
+            Some(&Occupant(Force(0), PhageType::Electro))
+        );
+    }
+
+    #[test]
+    pub fn staying_allies_block_movement() {
+        let mut map = line_map();
+        place(&mut map, 1, Force(0), PhageType::Electro);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1)],
+            ..Default::default()
+        };
+
+        let mut events = Vec::default();
+        let contests = movement(&mut map, &orders, &mut events);
+
+        assert!(events.is_empty());
+        assert!(contests.is_empty());
+    }
+
+    #[test]
+    pub fn opposing_swaps_block_each_other() {
+        let mut map = line_map();
+        place(&mut map, 1, Force(1), PhageType::Sonic);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1), order(1, 1, 0)],
+            ..Default::default()
+        };
+
+        let mut events = Vec::default();
+        let contests = movement(&mut map, &orders, &mut events);
+
+        assert!(events.is_empty());
+        assert!(contests.is_empty());
+    }
+
+    #[test]
+    pub fn defenders_win_ties() {
+        let mut map = line_map();
+        place(&mut map, 1, Force(1), PhageType::UV);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1)],
+            ..Default::default()
+        };
+
+        let (state, events) = transmit(&map, &orders);
+
+        assert_eq!(
+            events,
+            vec![TransmissionEvents::CombatOccured {
+                cell: NodeId(1),
+                victor: (Force(1), PhageType::UV),
+                lose: (Force(0), PhageType::UV),
+            }]
+        );
+        assert!(state.nodes[&NodeId(0)].occupant().is_none());
+        assert_eq!(
+            state.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(1), PhageType::UV))
+        );
+    }
+
+    #[test]
+    pub fn victorious_attackers_take_the_cell() {
+        let mut map = line_map();
+        place(&mut map, 1, Force(1), PhageType::Electro);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1)],
+            ..Default::default()
+        };
+
+        let (state, events) = transmit(&map, &orders);
+
+        assert_eq!(
+            events,
+            vec![
+                TransmissionEvents::CombatOccured {
+                    cell: NodeId(1),
+                    victor: (Force(0), PhageType::UV),
+                    lose: (Force(1), PhageType::Electro),
+                },
+                TransmissionEvents::PhageMoved {
+                    from: NodeId(0),
+                    to: NodeId(1)
+                },
+            ]
+        );
+        assert!(state.nodes[&NodeId(0)].occupant().is_none());
+        assert_eq!(
+            state.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(0), PhageType::UV))
+        );
+    }
+
+    #[test]
+    pub fn combat_uses_the_map_matchups() {
+        let mut map = line_map();
+        place(&mut map, 2, Force(1), PhageType::Sonic);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1), order(1, 2, 1)],
+            ..Default::default()
+        };
+
+        let (state, _) = transmit(&map, &orders);
+        assert_eq!(
+            state.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(1), PhageType::Sonic))
+        );
+
+        map.matchups.wins = vec![(PhageType::UV, PhageType::Sonic)];
+        let (state, _) = transmit(&map, &orders);
+        assert_eq!(
+            state.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(0), PhageType::UV))
+        );
+        assert!(state.nodes[&NodeId(2)].occupant().is_none());
+    }
+
+    #[test]
+    pub fn generators_pay_out_on_their_cadence() {
+        //Force(0) owns the whole line, Force(1) occupies the generator
+        let mut map = line_map();
+        map.nodes.get_mut(&NodeId(2)).unwrap().tenant = NodeTenant::Generator {
+            generator: Generator {
+                amt: 25,
+                speed: 2,
+                occupant: Some(Occupant(Force(1), PhageType::UV)),
+            },
+        };
+
+        let (state, events) = transmit(&map, &PendingOrders::default());
+        assert!(events.is_empty());
+        assert_eq!(state.qubits(Force(1)), 0);
+
+        let (state, events) = transmit(&state, &PendingOrders::default());
+        assert_eq!(
+            events,
+            vec![TransmissionEvents::QubitsDistributed {
+                recipient: Force(1),
+                qty: 25,
+                source: NodeId(2),
+            }]
+        );
+        assert_eq!(state.qubits(Force(1)), 25);
+        assert_eq!(state.qubits(Force(0)), 0);
+    }
+
+    #[test]
+    pub fn neutral_generators_pay_whoever_occupies_them() {
+        let mut map = line_map();
+        let generator = map.nodes.get_mut(&NodeId(2)).unwrap();
+        generator.force = Force::NEUTRAL;
+        generator.tenant = NodeTenant::Generator {
+            generator: Generator {
+                amt: 10,
+                speed: 1,
+                occupant: None,
+            },
+        };
+
+        //Nobody is paid while the generator stands empty
+        let (state, events) = transmit(&map, &PendingOrders::default());
+        assert!(events.is_empty());
+        assert!(state.qubits.is_empty());
+
+        //Phage can move onto a generator to claim its income
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1)],
+            ..Default::default()
+        };
+        let (state, _) = transmit(&state, &orders);
+        let orders = PendingOrders {
+            moves: vec![order(0, 1, 2)],
+            ..Default::default()
+        };
+        let (state, _) = transmit(&state, &orders);
+        assert_eq!(
+            state.nodes[&NodeId(2)].occupant(),
+            Some(&Occupant(Force(0), PhageType::UV))
+        );
+
+        let (state, events) = transmit(&state, &PendingOrders::default());
+        assert!(events.contains(&TransmissionEvents::QubitsDistributed {
+            recipient: Force(0),
+            qty: 10,
+            source: NodeId(2),
+        }));
+        assert_eq!(state.qubits(Force(0)), 10);
+        assert_eq!(state.qubits(Force::NEUTRAL), 0);
+    }
+
+    #[test]
+    pub fn recombinators_trigger_on_phase_counters() {
+        let mut map = line_map();
+        recombinator(
+            &mut map,
+            2,
+            RecombinatorTriggers::NumberOfTransmissionPhases { phases: 2 },
+            RecombinatorEffect::GiveOccupierQubits { amt: 5 },
+        );
+        place(&mut map, 2, Force(0), PhageType::UV);
+
+        let (state, events) = transmit(&map, &PendingOrders::default());
+        assert!(events.is_empty());
+
+        let (state, events) = transmit(&state, &PendingOrders::default());
+        assert!(matches!(
+            events[0],
+            TransmissionEvents::RecombinatorTriggered {
+                cell: NodeId(2),
+                ..
+            }
+        ));
+        assert_eq!(
+            events[1],
+            TransmissionEvents::QubitsDistributed {
+                recipient: Force(0),
+                qty: 5,
+                source: NodeId(2),
+            }
+        );
+        assert_eq!(state.qubits(Force(0)), 5);
+    }
+
+    #[test]
+    pub fn triggered_recombinators_fire_next_interval() {
+        let mut map = line_map();
+        recombinator(
+            &mut map,
+            2,
+            RecombinatorTriggers::CombatOccured,
+            RecombinatorEffect::GiveOccupierQubits { amt: 5 },
+        );
+        place(&mut map, 2, Force(0), PhageType::UV);
+
+        let trigger = Mutation::TriggerRecombinator {
+            target: NodeId(2),
+            cost: 0,
+        };
+        map.apply_mutation(Force(0), &trigger).unwrap();
+        assert_eq!(map.qubits(Force(0)), 0);
+
+        let (state, _) = transmit(&map, &PendingOrders::default());
+        assert_eq!(state.qubits(Force(0)), 5);
+
+        let (state, events) = transmit(&state, &PendingOrders::default());
+        assert!(events.is_empty());
+        assert_eq!(state.qubits(Force(0)), 5);
+
+        let not_a_recombinator = Mutation::TriggerRecombinator {
+            target: NodeId(1),
+            cost: 0,
+        };
+        assert!(matches!(
+            map.apply_mutation(Force(0), &not_a_recombinator),
+            Err(PlayerActionError::NotARecombinator(NodeId(1)))
+        ));
+    }
+
+    #[test]
+    pub fn recombinators_trigger_after_phage_enter() {
+        let mut map = line_map();
+        recombinator(
+            &mut map,
+            1,
+            RecombinatorTriggers::PhageEntered,
+            RecombinatorEffect::DestroyOccupierIfType {
+                phage_type: PhageType::Any,
+            },
+        );
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1)],
+            ..Default::default()
+        };
+
+        let (state, _) = transmit(&map, &orders);
+        assert!(state.nodes[&NodeId(1)].occupant().is_some());
+
+        let (state, events) = transmit(&state, &PendingOrders::default());
+        assert_eq!(
+            events[1],
+            TransmissionEvents::PhageDestroyed {
+                cell: NodeId(1),
+                phage: (Force(0), PhageType::UV),
+            }
+        );
+        assert!(state.nodes[&NodeId(1)].occupant().is_none());
+    }
+
+    #[test]
+    pub fn recombinators_give_defenders_advantage() {
+        let mut map = line_map();
+        recombinator(
+            &mut map,
+            1,
+            RecombinatorTriggers::OpposingNeighbors,
+            RecombinatorEffect::GiveOccupierAdvantage,
+        );
+        place(&mut map, 1, Force(1), PhageType::Electro);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1)],
+            ..Default::default()
+        };
+
+        let (state, _) = transmit(&map, &orders);
+
+        assert_eq!(
+            state.nodes[&NodeId(1)].occupant(),
+            Some(&Occupant(Force(1), PhageType::Electro))
+        );
+        assert!(state.nodes[&NodeId(0)].occupant().is_none());
+    }
+
+    #[test]
+    pub fn recombinators_pull_phage_when_vectors_are_added() {
+        let mut map = line_map();
+        recombinator(
+            &mut map,
+            3,
+            RecombinatorTriggers::VectorAdded { dest: NodeId(1) },
+            RecombinatorEffect::PullPhageForCombat(Vector::new(NodeId(3), NodeId(4))),
+        );
+        let mutation = Mutation::AddVector {
+            relation: Vector::new(NodeId(1), NodeId(3)),
+            cost: 0,
+        };
+        map.apply_mutation(Force(0), &mutation).unwrap();
+
+        let (state, events) = transmit(&map, &PendingOrders::default());
+
+        assert_eq!(
+            events.last(),
+            Some(&TransmissionEvents::PhageMoved {
+                from: NodeId(4),
+                to: NodeId(3)
+            })
+        );
+        assert_eq!(
+            state.nodes[&NodeId(3)].occupant(),
+            Some(&Occupant(Force(1), PhageType::Sonic))
+        );
+    }
+
+    #[test]
+    pub fn capturing_the_last_nexus_eliminates_a_force() {
+        let mut map = line_map();
+        map.nodes.get_mut(&NodeId(2)).unwrap().tenant = NodeTenant::Nexus {
+            nexus: Nexus { health: 2 },
+        };
+        map.nodes.get_mut(&NodeId(2)).unwrap().force = Force(1);
+        map.nodes.get_mut(&NodeId(4)).unwrap().tenant = NodeTenant::Nexus {
+            nexus: Nexus::default(),
+        };
+        place(&mut map, 1, Force(0), PhageType::UV);
+        place(&mut map, 3, Force(0), PhageType::UV);
+        let orders = PendingOrders {
+            moves: vec![order(0, 1, 2), order(0, 3, 2)],
+            ..Default::default()
+        };
+
+        let (state, events) = transmit(&map, &orders);
+
+        assert_eq!(
+            events,
+            vec![
+                TransmissionEvents::NexusDamaged {
+                    nexus: NodeId(2),
+                    attacker: (Force(0), PhageType::UV),
+                    health: 1,
+                },
+                TransmissionEvents::NexusDamaged {
+                    nexus: NodeId(2),
+                    attacker: (Force(0), PhageType::UV),
+                    health: Nexus::default().health,
+                },
+                TransmissionEvents::NexusCaptured {
+                    nexus: NodeId(2),
+                    from: Force(1),
+                    by: Force(0),
+                },
+                TransmissionEvents::ForceEliminated { force: Force(1) },
+            ]
+        );
+        assert_eq!(state.nodes[&NodeId(2)].force, Force(0));
+        assert!(state.nodes[&NodeId(1)].occupant().is_none());
+        assert!(state.nodes[&NodeId(3)].occupant().is_none());
+    }
+
+    #[test]
+    pub fn stepping_through_sub_phases_matches_transmit() {
+        //Node 1 is contested while Force(1) moves its other phage into a free cell
+        let mut map = line_map();
+        place(&mut map, 2, Force(1), PhageType::Electro);
+        let orders = PendingOrders {
+            moves: vec![order(0, 0, 1), order(1, 2, 1), order(1, 4, 3)],
+            ..Default::default()
+        };
+
+        let mut transmission = Transmission::begin(&map, &orders);
+        let mut stepped = Vec::default();
+        while let Some(sub_phase) = transmission.next_sub_phase() {
+            let events = transmission.step();
+            let fought = events
+                .iter()
+                .any(|event| matches!(event, TransmissionEvents::CombatOccured { .. }));
+            match sub_phase {
+                TransmissionSubPhase::Movement => {
+                    assert!(!fought);
+                    assert!(events.contains(&TransmissionEvents::PhageMoved {
+                        from: NodeId(4),
+                        to: NodeId(3),
+                    }));
+                    assert!(transmission.state().nodes[&NodeId(1)].occupant().is_none());
+                }
+                TransmissionSubPhase::Combat => assert!(fought),
+                _ => {}
+            }
+            stepped.extend(events);
+        }
+
+        let (state, events) = transmission.finish();
+        assert_eq!((state, events.clone()), transmit(&map, &orders));
+        assert_eq!(stepped, events);
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+
+use super::{gamerunner::GamePhase, Force, GameState, Mutation, NodeId, PlayerActionError, Vector};
+
+/// Per map limits on what players are allowed to mutate
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct MutationRules {
+    /// Longest vector, in world units, a player may add
+    pub max_vector_length: f32,
+
+    /// Most vectors a single node may be connected to after a player adds one
+    pub max_degree: usize,
+}
+
+impl Default for MutationRules {
+    fn default() -> Self {
+        MutationRules {
+            max_vector_length: 15.,
+            max_degree: 6,
+        }
+    }
+}
+
+/// Checks a mutation submitted by `force` against the rules of the game without applying it.
+/// Qubit costs and vector existence are checked when the mutation is applied
+pub fn validate_mutation(
+    state: &GameState,
+    force: Force,
+    mutation: &Mutation,
+    phase: GamePhase,
+) -> Result<(), PlayerActionError> {
+    if phase != GamePhase::MutationPhase {
+        return Err(PlayerActionError::WrongPhase(phase));
+    }
+
+    if state.eliminated.contains(&force) {
+        return Err(PlayerActionError::ForceEliminated(force));
+    }
+
+    if let Mutation::AddVector { relation, .. } = mutation {
+        validate_new_vector(state, *relation)?;
+    }
+
+    let tile = mutated_tile(state, force, mutation)?;
+    if state.mutated_tiles.contains(&(force, tile)) {
+        return Err(PlayerActionError::TileAlreadyMutated(tile));
+    }
+
+    Ok(())
+}
+
+/// The tile a mutation is charged against, the first node of the mutation `force` controls
+pub(crate) fn mutated_tile(
+    state: &GameState,
+    force: Force,
+    mutation: &Mutation,
+) -> Result<NodeId, PlayerActionError> {
+    let candidates = match mutation {
+        Mutation::TriggerRecombinator { target, .. } => vec![*target],
+        Mutation::ChangeReplicatorType { replicator, .. } => vec![*replicator],
+        Mutation::AddVector { relation, .. } | Mutation::RemoveVector { relation, .. } => {
+            vec![relation.0, relation.1]
+        }
+    };
+
+    for id in candidates.iter() {
+        let node = state
+            .nodes
+            .get(id)
+            .ok_or(PlayerActionError::NodeIdDoesNotExist(*id))?;
+        if node.force == force || node.occupant().map(|occupant| occupant.0) == Some(force) {
+            return Ok(*id);
+        }
+    }
+
+    Err(PlayerActionError::NotControlledBy {
+        node: candidates[0],
+        force,
+    })
+}
+
+fn validate_new_vector(state: &GameState, vector: Vector) -> Result<(), PlayerActionError> {
+    if vector.0 == vector.1 {
+        return Err(PlayerActionError::SelfLoop(vector.0));
+    }
+
+    let node = |id: NodeId| {
+        state
+            .nodes
+            .get(&id)
+            .ok_or(PlayerActionError::NodeIdDoesNotExist(id))
+    };
+    let (start, end) = (node(vector.0)?, node(vector.1)?);
+
+    let length = start.position.distance(end.position);
+    if length > state.mutation_rules.max_vector_length {
+        return Err(PlayerActionError::VectorTooLong {
+            length,
+            max: state.mutation_rules.max_vector_length,
+        });
+    }
+
+    for id in [vector.0, vector.1] {
+        if state.vectors.degree(id) >= state.mutation_rules.max_degree {
+            return Err(PlayerActionError::TooManyVectors {
+                node: id,
+                max: state.mutation_rules.max_degree,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{Cell, NodeTenant, Occupant, PhageType};
+
+    use super::*;
+
+    /// Three cells in a row, 0 and 1 are owned by Force(0) and 2 is owned by Force(1)
+    fn row_map() -> GameState {
+        let mut map = GameState::new("Row".to_string(), 2);
+        map.create_node(Force(0), Vec3::ZERO);
+        map.create_node(Force(0), Vec3::new(5., 0., 0.));
+        map.create_node(Force(1), Vec3::new(10., 0., 0.));
+        map
+    }
+
+    fn add_vector(from: u32, to: u32) -> Mutation {
+        Mutation::AddVector {
+            relation: Vector::new(NodeId(from), NodeId(to)),
+            cost: 0,
+        }
+    }
+
+    #[test]
+    pub fn mutations_only_happen_in_the_mutation_phase() {
+        let map = row_map();
+        assert!(matches!(
+            validate_mutation(
+                &map,
+                Force(0),
+                &add_vector(0, 1),
+                GamePhase::TransmissionPhase
+            ),
+            Err(PlayerActionError::WrongPhase(GamePhase::TransmissionPhase))
+        ));
+        assert!(
+            validate_mutation(&map, Force(0), &add_vector(0, 1), GamePhase::MutationPhase).is_ok()
+        );
+    }
+
+    #[test]
+    pub fn vectors_must_be_well_formed() {
+        let mut map = row_map();
+        assert!(matches!(
+            validate_mutation(&map, Force(0), &add_vector(1, 1), GamePhase::MutationPhase),
+            Err(PlayerActionError::SelfLoop(NodeId(1)))
+        ));
+
+        map.mutation_rules.max_vector_length = 6.;
+        assert!(matches!(
+            validate_mutation(&map, Force(0), &add_vector(0, 2), GamePhase::MutationPhase),
+            Err(PlayerActionError::VectorTooLong { .. })
+        ));
+
+        map.mutation_rules.max_degree = 1;
+        map.add_vector(Vector::new(NodeId(1), NodeId(2))).unwrap();
+        assert!(matches!(
+            validate_mutation(&map, Force(0), &add_vector(0, 1), GamePhase::MutationPhase),
+            Err(PlayerActionError::TooManyVectors {
+                node: NodeId(1),
+                max: 1
+            })
+        ));
+    }
+
+    #[test]
+    pub fn forces_mutate_tiles_they_control_once_per_phase() {
+        let mut map = row_map();
+        let trigger = Mutation::TriggerRecombinator {
+            target: NodeId(2),
+            cost: 0,
+        };
+        assert!(matches!(
+            validate_mutation(&map, Force(0), &trigger, GamePhase::MutationPhase),
+            Err(PlayerActionError::NotControlledBy {
+                node: NodeId(2),
+                force: Force(0)
+            })
+        ));
+
+        // Occupying an enemy tile lets a force mutate it
+        map.nodes.get_mut(&NodeId(2)).unwrap().tenant = NodeTenant::Cell {
+            cell: Cell {
+                occupant: Some(Occupant(Force(0), PhageType::UV)),
+            },
+        };
+        let mutation = add_vector(1, 2);
+        assert!(validate_mutation(&map, Force(0), &mutation, GamePhase::MutationPhase).is_ok());
+
+        map.apply_mutation(Force(0), &mutation).unwrap();
+        let remove = Mutation::RemoveVector {
+            relation: Vector::new(NodeId(1), NodeId(2)),
+            cost: 0,
+        };
+        assert!(matches!(
+            validate_mutation(&map, Force(0), &remove, GamePhase::MutationPhase),
+            Err(PlayerActionError::TileAlreadyMutated(NodeId(1)))
+        ));
+        assert!(
+            validate_mutation(&map, Force(0), &add_vector(0, 2), GamePhase::MutationPhase).is_ok()
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Force, GameState, NodeTenant};
+
+/// The rules deciding when a match is over and who won it
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum VictoryCondition {
+    /// Forces are eliminated when they lose their last nexus, the last force standing wins
+    #[default]
+    LastForceStanding,
+
+    /// Once `after_phases` transmission phases have run the surviving force with the
+    /// most qubits wins. Still ends early if only one force is left standing
+    MostQubits { after_phases: u32 },
+}
+
+/// Player forces that own at least one nexus, nexuses held by the neutral force don't count
+pub fn surviving_forces(state: &GameState) -> BTreeSet<Force> {
+    state
+        .nodes
+        .values()
+        .filter(|node| matches!(node.tenant, NodeTenant::Nexus { .. }))
+        .map(|node| node.force)
+        .filter(|force| !force.is_neutral())
+        .collect()
+}
+
+/// Decides whether the match described by `state` is over, returning the victor if it is.
+/// Ties on qubits go to the lowest force so every client agrees on the outcome
+pub fn victor(state: &GameState) -> Option<Force> {
+    let surviving = surviving_forces(state);
+    if !state.eliminated.is_empty() && surviving.len() == 1 {
+        return surviving.into_iter().next();
+    }
+
+    match state.victory {
+        VictoryCondition::LastForceStanding => None,
+        VictoryCondition::MostQubits { after_phases } => {
+            if state.transmission_phases < after_phases {
+                return None;
+            }
+            surviving
+                .into_iter()
+                .max_by_key(|force| (state.qubits(*force), Reverse(*force)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::game::{Nexus, NodeId};
+
+    use super::*;
+
+    fn two_nexus_map() -> GameState {
+        let mut map = GameState::new("Duel".to_string(), 2);
+        for force in [Force(0), Force(1)] {
+            let id = map.create_node(force, Vec3::ZERO);
+            map.nodes.get_mut(&id).unwrap().tenant = NodeTenant::Nexus {
+                nexus: Nexus::default(),
+            };
+        }
+        map
+    }
+
+    #[test]
+    pub fn last_force_standing_wins() {
+        let mut map = two_nexus_map();
+        assert_eq!(victor(&map), None);
+
+        map.nodes.get_mut(&NodeId(1)).unwrap().force = Force(0);
+        assert_eq!(victor(&map), None);
+
+        map.eliminated.insert(Force(1));
+        assert_eq!(victor(&map), Some(Force(0)));
+    }
+
+    #[test]
+    pub fn most_qubits_wins_after_enough_phases() {
+        let mut map = two_nexus_map();
+        map.victory = VictoryCondition::MostQubits { after_phases: 10 };
+        map.grant_qubits(Force(1), 20);
+        map.transmission_phases = 9;
+        assert_eq!(victor(&map), None);
+
+        map.transmission_phases = 10;
+        assert_eq!(victor(&map), Some(Force(1)));
+
+        map.grant_qubits(Force(0), 20);
+        assert_eq!(victor(&map), Some(Force(0)));
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gamerunner::GameRunnerRes, Force, GameState, LocalPlayerRes, Nexus, NodeId, NodeTenant,
+    RecombinatorObservations,
+};
+
+/// Per map rules for how much of the map each force can see
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct FogRules {
+    /// When disabled every force sees the whole map. Off unless a map opts in, so maps
+    /// written before fog existed play as they always have
+    pub enabled: bool,
+
+    /// Vector hops past the nodes a force owns or occupies that it can still see into
+    pub vision_hops: u32,
+}
+
+impl Default for FogRules {
+    fn default() -> Self {
+        FogRules {
+            enabled: false,
+            vision_hops: 2,
+        }
+    }
+}
+
+/// Nodes the local force can currently see, `None` when nothing is hidden because fog is
+/// disabled or no match is being played
+#[derive(Default)]
+pub struct FogOfWarRes {
+    pub visible: Option<BTreeSet<NodeId>>,
+}
+
+impl FogOfWarRes {
+    pub fn shows(&self, id: NodeId) -> bool {
+        match &self.visible {
+            Some(visible) => visible.contains(&id),
+            None => true,
+        }
+    }
+}
+
+/// Nodes `force` can see: every node it owns or occupies and everything within
+/// `vision_hops` vectors of them
+pub fn visible_nodes(state: &GameState, force: Force) -> BTreeSet<NodeId> {
+    if !state.fog.enabled {
+        return state.nodes.keys().copied().collect();
+    }
+
+    let mut visible: BTreeSet<NodeId> = state
+        .nodes
+        .values()
+        .filter(|node| {
+            node.force == force || node.occupant().is_some_and(|occupant| occupant.0 == force)
+        })
+        .map(|node| node.id)
+        .collect();
+
+    let mut frontier: Vec<NodeId> = visible.iter().copied().collect();
+    for _ in 0..state.fog.vision_hops {
+        frontier = frontier
+            .into_iter()
+            .flat_map(|id| state.get_all_neighbors(id))
+            .filter(|id| visible.insert(*id))
+            .collect();
+    }
+
+    visible
+}
+
+/// The state as `force` is allowed to know it: other forces' qubits and mutations outside
+/// its vision are dropped, nodes there are shown as neutral without occupants and their
+/// tenants forget anything that changes during play. This is all a peer playing `force`
+/// should ever be sent
+pub fn visible_state(state: &GameState, force: Force) -> GameState {
+    if !state.fog.enabled {
+        return state.clone();
+    }
+
+    let visible = visible_nodes(state, force);
+    let mut redacted = state.clone();
+    redacted.qubits.retain(|owner, _| *owner == force);
+    redacted
+        .mutated_tiles
+        .retain(|(_, id)| visible.contains(id));
+
+    for node in redacted.nodes.values_mut() {
+        if visible.contains(&node.id) {
+            continue;
+        }
+        //Captured nexuses change hands, so no owner is shown through the fog
+        node.force = Force::NEUTRAL;
+        if let Some(occupant) = node.occupant_slot_mut() {
+            *occupant = None;
+        }
+        match &mut node.tenant {
+            NodeTenant::Recombinator { recombinator } => {
+                recombinator.observed = RecombinatorObservations::default()
+            }
+            NodeTenant::Replicator { replicator } => replicator.phases_waited = 0,
+            //Fogged nexuses look untouched
+            NodeTenant::Nexus { nexus } => *nexus = Nexus::default(),
+            NodeTenant::Cell { .. } | NodeTenant::Generator { .. } => {}
+        }
+    }
+
+    redacted
+}
+
+/// Recomputes what the local force can see whenever the map or the local force changes
+pub(crate) fn update_fog_of_war(
+    maps: Query<&GameState>,
+    runner: Res<GameRunnerRes>,
+    local_player: Res<LocalPlayerRes>,
+    mut fog: ResMut<FogOfWarRes>,
+) {
+    let visible = match maps.get_single() {
+        Ok(state) if runner.run_game && state.fog.enabled => {
+            Some(visible_nodes(state, local_player.force))
+        }
+        _ => None,
+    };
+
+    //Only write on a change so rendering can rely on change detection
+    if fog.visible != visible {
+        fog.visible = visible;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bevy::prelude::Vec3;
+
+    use crate::game::{Occupant, PhageType, Vector};
+
+    use super::*;
+
+    #[test]
+    pub fn forces_only_see_nearby_nodes() {
+        //A line of nodes, Force(0) owns the first, Force(1) owns a worn down nexus on the
+        //second to last and occupies the last
+        let mut map = GameState::new("Fog".to_string(), 2);
+        let line: Vec<NodeId> = (0..7)
+            .map(|i| map.create_node(Force::NEUTRAL, Vec3::X * i as f32))
+            .collect();
+        for pair in line.windows(2) {
+            map.add_vector(Vector::new(pair[0], pair[1])).unwrap();
+        }
+        map.nodes.get_mut(&line[0]).unwrap().force = Force(0);
+        let nexus = map.nodes.get_mut(&line[5]).unwrap();
+        nexus.force = Force(1);
+        nexus.tenant = NodeTenant::Nexus {
+            nexus: Nexus { health: 2 },
+        };
+        *map.nodes
+            .get_mut(&line[6])
+            .unwrap()
+            .occupant_slot_mut()
+            .unwrap() = Some(Occupant(Force(1), PhageType::UV));
+        map.qubits = BTreeMap::from([(Force(0), 3), (Force(1), 7)]);
+        map.mutated_tiles = BTreeSet::from([(Force(0), line[1]), (Force(1), line[5])]);
+        map.fog.enabled = true;
+        map.fog.vision_hops = 2;
+
+        assert_eq!(
+            visible_nodes(&map, Force(0)),
+            BTreeSet::from([line[0], line[1], line[2]])
+        );
+        assert_eq!(
+            visible_nodes(&map, Force(1)),
+            BTreeSet::from([line[3], line[4], line[5], line[6]])
+        );
+
+        let seen = visible_state(&map, Force(0));
+        assert_eq!(seen.nodes[&line[6]].occupant(), None);
+        assert_eq!(seen.nodes[&line[5]].force, Force::NEUTRAL);
+        assert_eq!(seen.qubits, BTreeMap::from([(Force(0), 3)]));
+        assert_eq!(seen.mutated_tiles, BTreeSet::from([(Force(0), line[1])]));
+        assert_eq!(
+            seen.nodes[&line[5]].tenant,
+            NodeTenant::Nexus {
+                nexus: Nexus::default()
+            }
+        );
+
+        let seen = visible_state(&map, Force(1));
+        for id in &line[3..] {
+            assert_eq!(seen.nodes[id], map.nodes[id]);
+        }
+        assert_eq!(seen.nodes[&line[0]].force, Force::NEUTRAL);
+        assert_eq!(seen.qubits, BTreeMap::from([(Force(1), 7)]));
+
+        //A third force capturing the fogged nexus goes unnoticed
+        let before = visible_state(&map, Force(0));
+        let nexus = map.nodes.get_mut(&line[5]).unwrap();
+        nexus.force = Force(2);
+        nexus.tenant = NodeTenant::Nexus {
+            nexus: Nexus::default(),
+        };
+        assert_eq!(visible_state(&map, Force(0)), before);
+
+        map.fog.enabled = false;
+        assert_eq!(visible_nodes(&map, Force(0)).len(), line.len());
+        assert_eq!(visible_state(&map, Force(0)), map);
+    }
+}
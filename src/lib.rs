@@ -1,5 +1,9 @@
+#[cfg(feature = "client")]
 pub mod audio;
 pub mod game;
+#[cfg(feature = "client")]
 pub mod net;
+#[cfg(feature = "client")]
 pub mod ui;
+#[cfg(feature = "client")]
 pub mod util;
@@ -1,5 +1,5 @@
 use bevy::ecs::query::QuerySingleError;
-use bevy::{prelude::*, window::PresentMode};
+use bevy::{asset::AssetServerSettings, prelude::*, window::PresentMode};
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::{WorldInspectorParams, WorldInspectorPlugin};
 use bevy_mod_picking::*;
@@ -9,10 +9,13 @@ use macrophage::{
     audio::GameAudioPlugin,
     game::{
         controller::PlayerAction,
+        gamerunner::MacroPhageGamerunnerPlugin,
         mutationinput::{mutation_input, mutation_selection, MutationSelection},
-        PlayerMutationEvent,
     },
-    game::{map::spawn_map, LevelManagerRes},
+    game::{
+        map::{spawn_map, MapAssetPlugin},
+        LevelManagerRes,
+    },
     ui::mousecursor_egui::MouseCursorPlugin,
     ui::UIStatePlugin,
     util::{camera::MacroCamPlugin, MacroUtils},
@@ -44,6 +47,11 @@ fn main() {
         .init_resource::<ReadWriteGameSettings>()
         .add_plugin(SettingsPlugin)
         .add_plugin(UIStatePlugin)
+        //Hot reload maps and other assets when they change on disk
+        .insert_resource(AssetServerSettings {
+            watch_for_changes: true,
+            ..default()
+        })
         .add_plugins(DefaultPlugins)
         //Egui (must be before inspector)
         .add_plugin(EguiPlugin)
@@ -62,14 +70,14 @@ fn main() {
         //.add_plugin(NoCameraPlayerPlugin)
         .add_plugin(MacroUtils {})
         //Test scene spawning
+        .add_plugin(MapAssetPlugin)
         .add_system(spawn_map)
         .add_system(macrophage::game::spawn_player)
         //Things that need keybinds should load after the player
         .add_plugin(MacroCamPlugin {})
         .add_system(toggle_inspector)
         .add_plugin(PhageSelectPlugin)
-        //TODO move me to where I belong, game runner is not currently added
-        .add_event::<PlayerMutationEvent>()
+        .add_plugin(MacroPhageGamerunnerPlugin {})
         .init_resource::<MutationSelection>()
         .add_system(mutation_selection)
         .add_system(mutation_input)
@@ -0,0 +1,314 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    game::{
+        controller::PlayerAction,
+        editor::{MapEditor, TenantKind},
+        Force, GameState, LevelManagerRes, NodeId, PhageType, PlayerActionError,
+    },
+    util::{
+        camera::{CameraState, PlayerCamMarker},
+        mouse::MousePosition,
+        ui::set_ui_style,
+        MapManifest,
+    },
+};
+
+use super::{UIState, UIStateRes};
+
+/// How close to a node or vector, in world units, the cursor has to be to pick it
+const PICK_RADIUS: f32 = 1.;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EditorTool {
+    /// Places new nodes, or repaints the node under the cursor
+    Place,
+    /// Drag from one node to another to add a vector
+    Connect,
+    /// Deletes the node or vector under the cursor
+    Delete,
+}
+
+pub struct EditorStateRes {
+    pub file_name: String,
+    pub tool: EditorTool,
+    pub force: Force,
+    pub tenant: TenantKind,
+    pub phage: PhageType,
+    /// The map being edited, `None` until the selected map has loaded
+    pub editor: Option<MapEditor>,
+    drag_from: Option<NodeId>,
+    message: Option<String>,
+}
+
+impl Default for EditorStateRes {
+    fn default() -> Self {
+        EditorStateRes {
+            file_name: "new_map.json".to_string(),
+            tool: EditorTool::Place,
+            force: Force(0),
+            tenant: TenantKind::Cell,
+            phage: PhageType::UV,
+            editor: None,
+            drag_from: None,
+            message: None,
+        }
+    }
+}
+
+impl EditorStateRes {
+    fn report(&mut self, result: Result<(), PlayerActionError>) {
+        if let Err(reason) = result {
+            self.message = Some(format!("{:?}", reason));
+        }
+    }
+}
+
+/// Tool, tenant and file controls of the map editor
+pub fn map_editor(
+    mut egui_context: ResMut<EguiContext>,
+    mut ui_state: ResMut<UIStateRes>,
+    mut editor_state: ResMut<EditorStateRes>,
+    mut level_manager: ResMut<LevelManagerRes>,
+    maps_manifest: Res<MapManifest>,
+    mut player_cam: Query<(&mut CameraState, &PlayerCamMarker)>,
+    actions: Query<&ActionState<PlayerAction>>,
+) {
+    let editor_state = &mut *editor_state;
+
+    egui::SidePanel::left("Map Editor").show(egui_context.ctx_mut(), |ui| {
+        set_ui_style(ui);
+        ui.heading("Map Editor");
+
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut editor_state.file_name);
+        });
+
+        egui::ComboBox::from_label("Open")
+            .selected_text(level_manager.current_level.clone().unwrap_or_default())
+            .show_ui(ui, |ui| {
+                for map in maps_manifest.map_files.iter() {
+                    if ui.selectable_label(false, map).clicked() {
+                        editor_state.file_name = map.clone();
+                        editor_state.editor = None;
+                        level_manager.current_level = Some(map.clone());
+                    }
+                }
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("New").clicked() {
+                let name = editor_state.file_name.trim_end_matches(".json").to_string();
+                let editor = MapEditor::new(GameState::new(name, 2));
+                match editor.save(&editor_state.file_name) {
+                    Ok(()) => {
+                        editor_state.editor = None;
+                        level_manager.current_level = Some(editor_state.file_name.clone());
+                    }
+                    Err(err) => editor_state.message = Some(err.to_string()),
+                }
+            }
+
+            let save = ui.add_enabled(editor_state.editor.is_some(), egui::Button::new("Save"));
+            if save.clicked() {
+                if let Some(editor) = editor_state.editor.as_ref() {
+                    editor_state.message = Some(match editor.save(&editor_state.file_name) {
+                        Ok(()) => format!("Saved {}", editor_state.file_name),
+                        Err(err) => err.to_string(),
+                    });
+                }
+            }
+        });
+
+        ui.separator();
+
+        if let Some(editor) = editor_state.editor.as_mut() {
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut editor.state.name);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Players");
+                ui.add(egui::DragValue::new(&mut editor.state.num_players).clamp_range(1..=6));
+            });
+
+            let metadata = &mut editor.state.metadata;
+            ui.horizontal(|ui| {
+                ui.label("Author");
+                ui.text_edit_singleline(&mut metadata.author);
+            });
+            ui.label("Description");
+            ui.text_edit_multiline(&mut metadata.description);
+            ui.horizontal(|ui| {
+                ui.label("Recommended players");
+                ui.add(egui::DragValue::new(&mut metadata.min_players).clamp_range(1..=6));
+                ui.label("to");
+                ui.add(egui::DragValue::new(&mut metadata.max_players).clamp_range(1..=6));
+            });
+
+            let fog = &mut editor.state.fog;
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut fog.enabled, "Fog of war");
+                ui.add_enabled(
+                    fog.enabled,
+                    egui::DragValue::new(&mut fog.vision_hops).suffix(" hops of vision"),
+                );
+            });
+            ui.separator();
+        }
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut editor_state.tool, EditorTool::Place, "Place");
+            ui.radio_value(&mut editor_state.tool, EditorTool::Connect, "Connect");
+            ui.radio_value(&mut editor_state.tool, EditorTool::Delete, "Delete");
+        });
+
+        egui::ComboBox::from_label("Tenant")
+            .selected_text(format!("{:?}", editor_state.tenant))
+            .show_ui(ui, |ui| {
+                for kind in TenantKind::ALL {
+                    ui.selectable_value(&mut editor_state.tenant, kind, format!("{:?}", kind));
+                }
+            });
+
+        if editor_state.tenant == TenantKind::Replicator {
+            egui::ComboBox::from_label("Output")
+                .selected_text(format!("{:?}", editor_state.phage))
+                .show_ui(ui, |ui| {
+                    for phage in [PhageType::UV, PhageType::Electro, PhageType::Sonic] {
+                        ui.selectable_value(&mut editor_state.phage, phage, format!("{:?}", phage));
+                    }
+                });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Force");
+            let mut neutral = editor_state.force.is_neutral();
+            if ui.checkbox(&mut neutral, "Neutral").changed() {
+                editor_state.force = if neutral { Force::NEUTRAL } else { Force(0) };
+            }
+            if !neutral {
+                ui.add(egui::DragValue::new(&mut editor_state.force.0).clamp_range(0..=9));
+            }
+        });
+
+        if let Some(editor) = editor_state.editor.as_ref() {
+            for (slot, spawn) in editor.state.spawn_slots.iter().enumerate() {
+                ui.label(format!(
+                    "Slot {}: force {} at nexus {:?}",
+                    slot + 1,
+                    spawn.force.0,
+                    spawn.nexus
+                ));
+            }
+        }
+
+        ui.separator();
+
+        let actions = actions.get_single().ok();
+        let pressed = |action| actions.is_some_and(|actions| actions.just_pressed(action));
+        if let Some(editor) = editor_state.editor.as_mut() {
+            ui.horizontal(|ui| {
+                let undo = ui.add_enabled(editor.can_undo(), egui::Button::new("Undo"));
+                if undo.clicked() || pressed(PlayerAction::Undo) {
+                    editor.undo();
+                }
+                let redo = ui.add_enabled(editor.can_redo(), egui::Button::new("Redo"));
+                if redo.clicked() || pressed(PlayerAction::Redo) {
+                    editor.redo();
+                }
+            });
+        }
+
+        if let Some(from) = editor_state.drag_from {
+            ui.label(format!("Connecting from node {:?}", from));
+        }
+        if let Some(message) = editor_state.message.as_ref() {
+            ui.label(message);
+        }
+
+        ui.separator();
+        if ui.button("Back").clicked() {
+            *editor_state = EditorStateRes::default();
+            level_manager.current_level = None;
+            ui_state.current_state = UIState::MainMenu;
+        }
+    });
+
+    if let Ok((mut cam_state, _)) = player_cam.get_single_mut() {
+        cam_state.should_pan = true;
+        cam_state.should_zoom = true;
+    }
+}
+
+/// Places, connects and deletes nodes under the cursor, then pushes the edited map to the
+/// map asset so it is respawned
+pub fn map_editor_input(
+    mut egui_context: ResMut<EguiContext>,
+    mut editor_state: ResMut<EditorStateRes>,
+    level_manager: Res<LevelManagerRes>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mouse: Res<MousePosition>,
+    asset_server: Res<AssetServer>,
+    mut map_assets: ResMut<Assets<GameState>>,
+) {
+    let handle = match level_manager.current_level.as_ref() {
+        Some(name) => asset_server.get_handle::<GameState, _>(&format!("maps/{}", name)),
+        None => return,
+    };
+
+    let editor_state = &mut *editor_state;
+    let editor = match editor_state.editor.as_mut() {
+        Some(editor) => editor,
+        None => {
+            editor_state.editor = map_assets.get(&handle).cloned().map(MapEditor::new);
+            return;
+        }
+    };
+
+    if !egui_context.ctx_mut().wants_pointer_input() {
+        let position = Vec3::new(mouse.world.x, 0., mouse.world.z);
+        let hovered = editor.node_at(position, PICK_RADIUS);
+        let tenant = editor_state.tenant.tenant(editor_state.phage);
+
+        let result = match editor_state.tool {
+            EditorTool::Place if mouse_buttons.just_pressed(MouseButton::Left) => match hovered {
+                Some(id) => editor.repaint_node(id, editor_state.force, tenant),
+                None => {
+                    editor.place_node(editor_state.force, tenant, position);
+                    Ok(())
+                }
+            },
+            EditorTool::Connect if mouse_buttons.just_pressed(MouseButton::Left) => {
+                editor_state.drag_from = hovered;
+                Ok(())
+            }
+            EditorTool::Connect if mouse_buttons.just_released(MouseButton::Left) => {
+                match (editor_state.drag_from.take(), hovered) {
+                    (Some(from), Some(to)) if from != to => editor.connect(from, to),
+                    _ => Ok(()),
+                }
+            }
+            EditorTool::Delete if mouse_buttons.just_pressed(MouseButton::Left) => {
+                match (hovered, editor.vector_at(position, PICK_RADIUS)) {
+                    (Some(id), _) => editor.delete_node(id),
+                    (None, Some(vector)) => editor.disconnect(vector),
+                    (None, None) => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        };
+        editor_state.report(result);
+    }
+
+    //Writing the asset respawns the map, so only do it when something changed
+    let editor = editor_state.editor.as_ref().unwrap();
+    if map_assets.get(&handle) != Some(&editor.state) {
+        if let Some(map) = map_assets.get_mut(&handle) {
+            *map = editor.state.clone();
+        }
+    }
+}
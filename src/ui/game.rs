@@ -5,7 +5,12 @@ use bevy_inspector_egui::egui;
 
 use crate::util::ui::{set_ui_style, set_ui_style_none};
 use crate::{
-    game::LevelManagerRes,
+    game::{
+        checksum::DesyncDetected,
+        gamerunner::{GameMoveRejected, GameRunnerEvent, GameRunnerRes},
+        matchconfig::MatchConfig,
+        Force, LevelManagerRes, LocalPlayerRes, MutationFailed,
+    },
     util::camera::{CameraState, PlayerCamMarker},
 };
 
@@ -42,11 +47,14 @@ impl FromWorld for Images {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn game_hud(
+    mut commands: Commands,
     mut egui_context: ResMut<EguiContext>,
     mut ui_state: ResMut<UIStateRes>,
     mut player_cam: Query<(&mut CameraState, &PlayerCamMarker)>,
     mut level_manager: ResMut<LevelManagerRes>,
+    mut runner: ResMut<GameRunnerRes>,
     mut is_initialized: Local<bool>,
     mut images: Local<Images>,
 ) {
@@ -90,6 +98,8 @@ pub fn game_hud(
 
                 level_manager.current_level = None;
                 ui_state.current_state = UIState::MainMenu;
+                runner.run_game = false;
+                commands.remove_resource::<MatchConfig>();
             }
         });
 
@@ -151,3 +161,102 @@ pub fn game_hud(
             ui.image(images.quibit_icon_id, egui::vec2(50., 50.));
         });
 }
+
+/// How long the explanation of a rejected action stays on screen
+const NOTICE_SECONDS: f32 = 4.;
+
+/// Explains why the game refused the local player's last move or mutation, and warns when
+/// a peer's state no longer matches ours
+pub fn action_notices(
+    mut egui_context: ResMut<EguiContext>,
+    time: Res<Time>,
+    local_player: Res<LocalPlayerRes>,
+    mut mutation_failures: EventReader<MutationFailed>,
+    mut rejections: EventReader<GameMoveRejected>,
+    mut desyncs: EventReader<DesyncDetected>,
+    mut notice: Local<Option<(String, f32)>>,
+) {
+    let force = local_player.force;
+    let latest = mutation_failures
+        .iter()
+        .filter(|ev| ev.force == force)
+        .map(|ev| ev.reason.to_string())
+        .chain(
+            rejections
+                .iter()
+                .filter(|ev| ev.force == force)
+                .map(|ev| ev.reason.to_string()),
+        )
+        .chain(desyncs.iter().map(|ev| {
+            format!(
+                "Out of sync with player {} after phase {}: {}",
+                ev.peer.0 + 1,
+                ev.phase,
+                ev.desync
+            )
+        }))
+        .last();
+    if let Some(text) = latest {
+        *notice = Some((text, NOTICE_SECONDS));
+    }
+
+    let (text, remaining) = match notice.as_mut() {
+        Some(notice) => notice,
+        None => return,
+    };
+    *remaining -= time.delta_seconds();
+    if *remaining <= 0. {
+        *notice = None;
+        return;
+    }
+
+    egui::Area::new("Action Notice")
+        .anchor(Align2::CENTER_BOTTOM, egui::vec2(0., -140.))
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(egui::RichText::new(text.as_str()).color(egui::Color32::LIGHT_RED));
+        });
+}
+
+/// Announces the victor once the game runner reports the game is over
+pub fn game_over(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut ui_state: ResMut<UIStateRes>,
+    mut player_cam: Query<(&mut CameraState, &PlayerCamMarker)>,
+    mut level_manager: ResMut<LevelManagerRes>,
+    mut runner_events: EventReader<GameRunnerEvent>,
+    mut victor: Local<Option<Force>>,
+) {
+    for ev in runner_events.iter() {
+        match ev {
+            GameRunnerEvent::GameCompleted { victor: force } => *victor = Some(*force),
+            GameRunnerEvent::GameBegun => *victor = None,
+            _ => {}
+        }
+    }
+
+    let force = match *victor {
+        Some(force) => force,
+        None => return,
+    };
+
+    egui::Window::new("Game Over")
+        .anchor(Align2::CENTER_CENTER, egui::vec2(0., 0.))
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(format!("Player {} is victorious!", force.0 + 1));
+
+            if ui.button("Return to Main Menu").clicked() {
+                let (mut cam_state, _) = player_cam.single_mut();
+
+                cam_state.should_pan = false;
+                cam_state.should_zoom = false;
+
+                level_manager.current_level = None;
+                ui_state.current_state = UIState::MainMenu;
+                commands.remove_resource::<MatchConfig>();
+                *victor = None;
+            }
+        });
+}
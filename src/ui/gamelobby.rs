@@ -3,14 +3,20 @@ use bevy_egui::{
     EguiContext,
 };
 
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
 use bevy_egui::egui::style::Margin;
 use bevy_egui::egui::{Color32, Frame, RichText, Stroke};
 use bevy_inspector_egui::egui;
 
 use crate::util::ui::set_ui_style;
 use crate::{
-    game::LevelManagerRes,
+    game::{
+        mapinfo::preview_image,
+        mapvalidation::MapDiagnosticsRes,
+        matchconfig::{MatchConfig, PlayerSlot, RulesVariant, StartingResources, UnfilledSlots},
+        settings::ReadWriteGameSettings,
+        Force, GameState, LevelManagerRes,
+    },
     util::{
         camera::{CameraState, PlayerCamMarker},
         MapManifest,
@@ -41,12 +47,98 @@ impl FromWorld for Images {
     }
 }
 
+/// Who takes a spawn slot of the selected map
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Seat {
+    You,
+    Bot,
+    Empty,
+}
+
+#[derive(Default)]
 pub struct LobbyStateRes {
     pub selected_map: Option<String>,
+    /// Only list maps meant for this many players
+    pub player_filter: Option<u32>,
+    /// The force of every spawn slot of the selected map and who takes it
+    pub seats: Vec<(Force, Seat)>,
+    pub unfilled: UnfilledSlots,
+    pub starting: StartingResources,
+    pub rules: RulesVariant,
+}
+
+/// Size in pixels of map previews shown in the lobby
+const PREVIEW_SIZE: u32 = 128;
+
+/// Map assets the lobby has asked for, along with previews of them for egui
+#[derive(Default)]
+pub struct LobbyMapCache {
+    handles: HashMap<String, Handle<GameState>>,
+    previews: HashMap<String, egui::TextureId>,
+}
+
+#[derive(SystemParam)]
+pub struct LobbyMaps<'w, 's> {
+    asset_server: Res<'w, AssetServer>,
+    map_assets: Res<'w, Assets<GameState>>,
+    map_events: EventReader<'w, 's, AssetEvent<GameState>>,
+    image_assets: ResMut<'w, Assets<Image>>,
+    cache: Local<'s, LobbyMapCache>,
+}
+
+impl<'w, 's> LobbyMaps<'w, 's> {
+    /// The map in `assets/maps/<file_name>`, `None` until it has loaded
+    fn get(&mut self, file_name: &str) -> Option<&GameState> {
+        let asset_server = &self.asset_server;
+        let handle = self
+            .cache
+            .handles
+            .entry(file_name.to_string())
+            .or_insert_with(|| asset_server.load(&format!("maps/{}", file_name)));
+        self.map_assets.get(handle)
+    }
+
+    /// The map's own preview image, or a top-down picture of it
+    fn preview(
+        &mut self,
+        file_name: &str,
+        egui_context: &mut EguiContext,
+    ) -> Option<egui::TextureId> {
+        if let Some(preview) = self.cache.previews.get(file_name) {
+            return Some(*preview);
+        }
+
+        let map = self.get(file_name)?;
+        let image = match map.metadata.preview.clone() {
+            Some(path) => self.asset_server.load(&path),
+            None => {
+                let image = preview_image(map, PREVIEW_SIZE);
+                self.image_assets.add(image)
+            }
+        };
+        let preview = egui_context.add_image(image);
+        self.cache.previews.insert(file_name.to_string(), preview);
+        Some(preview)
+    }
+
+    /// Drops previews of maps that changed on disk so they are drawn again
+    fn forget_modified(&mut self) {
+        for event in self.map_events.iter() {
+            if let AssetEvent::Modified { handle } = event {
+                let cache = &mut *self.cache;
+                for (file_name, loaded) in cache.handles.iter() {
+                    if loaded == handle {
+                        cache.previews.remove(file_name);
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn lobby(
+    mut commands: Commands,
     mut egui_context: ResMut<EguiContext>,
     windows: Res<Windows>,
     mut ui_state: ResMut<UIStateRes>,
@@ -54,6 +146,10 @@ pub fn lobby(
     mut lobby_state: ResMut<LobbyStateRes>,
     mut player_cam: Query<(&mut CameraState, &PlayerCamMarker)>,
     mut level_manager: ResMut<LevelManagerRes>,
+    game_settings: Res<ReadWriteGameSettings>,
+    loaded_maps: Query<&GameState>,
+    diagnostics: Res<MapDiagnosticsRes>,
+    mut lobby_maps: LobbyMaps,
     mut is_initialized: Local<bool>,
     mut images: Local<Images>,
 ) {
@@ -63,6 +159,12 @@ pub fn lobby(
         images.main_menu_id = egui_context.add_image(images.main_menu.clone_weak());
     }
 
+    lobby_maps.forget_modified();
+    let preview = lobby_state
+        .selected_map
+        .clone()
+        .and_then(|file_name| lobby_maps.preview(&file_name, &mut egui_context));
+
     let main_window = windows.get_primary().unwrap();
     let window_width_margin = egui_context.ctx_mut().style().spacing.window_margin.left * 2.0;
 
@@ -82,11 +184,178 @@ pub fn lobby(
         set_ui_style(ui);
         let btn_size = egui::vec2(BTN_SIZE.0, BTN_SIZE.1);
 
-        egui::ScrollArea::vertical().show(ui, |ui| {
-            for map in &maps_manifest.map_files {
-                ui.radio_value(&mut lobby_state.selected_map, Some(map.clone()), map);
+        egui::ComboBox::from_label("Players")
+            .selected_text(match lobby_state.player_filter {
+                Some(players) => players.to_string(),
+                None => "Any".to_string(),
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut lobby_state.player_filter, None, "Any");
+                for players in 1..=6 {
+                    ui.selectable_value(
+                        &mut lobby_state.player_filter,
+                        Some(players),
+                        players.to_string(),
+                    );
+                }
+            });
+
+        egui::ScrollArea::vertical()
+            .max_height(200.)
+            .show(ui, |ui| {
+                for file_name in &maps_manifest.map_files {
+                    let label = match lobby_maps.get(file_name) {
+                        Some(map) => {
+                            let metadata = &map.metadata;
+                            if let Some(players) = lobby_state.player_filter {
+                                if !metadata.supports(players) {
+                                    continue;
+                                }
+                            }
+                            match metadata.min_players == metadata.max_players {
+                                true => format!("{} ({} players)", map.name, metadata.max_players),
+                                false => format!(
+                                    "{} ({}-{} players)",
+                                    map.name, metadata.min_players, metadata.max_players
+                                ),
+                            }
+                        }
+                        None => file_name.clone(),
+                    };
+                    ui.radio_value(
+                        &mut lobby_state.selected_map,
+                        Some(file_name.clone()),
+                        label,
+                    );
+                }
+            });
+
+        let selected = lobby_state.selected_map.clone();
+        if let Some(map) = selected.and_then(|file_name| lobby_maps.get(&file_name)) {
+            ui.separator();
+            ui.horizontal(|ui| {
+                if let Some(preview) = preview {
+                    ui.image(
+                        preview,
+                        egui::vec2(PREVIEW_SIZE as f32, PREVIEW_SIZE as f32),
+                    );
+                }
+
+                ui.vertical(|ui| {
+                    ui.heading(&map.name);
+                    if !map.metadata.author.is_empty() {
+                        ui.label(format!("by {}", map.metadata.author));
+                    }
+                    if !map.metadata.description.is_empty() {
+                        ui.label(&map.metadata.description);
+                    }
+                    if !map.metadata.tags.is_empty() {
+                        ui.label(map.metadata.tags.join(", "));
+                    }
+                });
+            });
+        }
+
+        if diagnostics.map.is_some() && diagnostics.map == lobby_state.selected_map {
+            for diagnostic in diagnostics.diagnostics.iter() {
+                let color = match diagnostic.is_error() {
+                    true => Color32::RED,
+                    false => Color32::YELLOW,
+                };
+                ui.colored_label(color, diagnostic.to_string());
             }
-        });
+        }
+
+        let mut players = None;
+        if let Ok(map) = loaded_maps.get_single() {
+            ui.separator();
+            let lobby_state = &mut *lobby_state;
+
+            //Seat you in the first slot whenever a map with different slots is selected
+            let forces = map.spawn_slots.iter().map(|slot| slot.force);
+            if !lobby_state.seats.iter().map(|(force, _)| *force).eq(forces) {
+                lobby_state.seats = map
+                    .spawn_slots
+                    .iter()
+                    .enumerate()
+                    .map(|(index, slot)| match index {
+                        0 => (slot.force, Seat::You),
+                        _ => (slot.force, Seat::Empty),
+                    })
+                    .collect();
+            }
+
+            let mut chosen = None;
+            for (index, (_, seat)) in lobby_state.seats.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Slot {}", index + 1));
+                    if ui.radio_value(seat, Seat::You, "You").clicked() {
+                        chosen = Some(index);
+                    }
+                    ui.radio_value(seat, Seat::Bot, "Bot");
+                    ui.radio_value(seat, Seat::Empty, "Empty");
+                });
+            }
+            //Only one slot can be yours
+            if let Some(chosen) = chosen {
+                for (index, (_, seat)) in lobby_state.seats.iter_mut().enumerate() {
+                    if index != chosen && *seat == Seat::You {
+                        *seat = Seat::Empty;
+                    }
+                }
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Empty slots");
+                ui.radio_value(&mut lobby_state.unfilled, UnfilledSlots::Neutral, "Neutral");
+                ui.radio_value(&mut lobby_state.unfilled, UnfilledSlots::Remove, "Removed");
+            });
+
+            let mut bots = 0;
+            players = Some(
+                lobby_state
+                    .seats
+                    .iter()
+                    .filter_map(|(force, seat)| {
+                        let name = match seat {
+                            Seat::You => game_settings.actual_profile.name.clone(),
+                            Seat::Bot => {
+                                bots += 1;
+                                format!("Bot {}", bots)
+                            }
+                            Seat::Empty => return None,
+                        };
+                        Some(PlayerSlot {
+                            name,
+                            force: *force,
+                            local: *seat == Seat::You,
+                            bot: *seat == Seat::Bot,
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            );
+
+            ui.horizontal(|ui| {
+                ui.label("Starting qubits");
+                ui.add(egui::DragValue::new(&mut lobby_state.starting.qubits));
+            });
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut lobby_state.rules, RulesVariant::Standard, "Standard");
+                ui.radio_value(
+                    &mut lobby_state.rules,
+                    RulesVariant::TurnBased,
+                    "Turn Based",
+                );
+                let economic = matches!(lobby_state.rules, RulesVariant::Economic { .. });
+                if ui.radio(economic, "Economic").clicked() && !economic {
+                    lobby_state.rules = RulesVariant::Economic { after_phases: 60 };
+                }
+                if let RulesVariant::Economic { after_phases } = &mut lobby_state.rules {
+                    ui.add(egui::DragValue::new(after_phases).suffix(" phases"));
+                }
+            });
+        }
 
         ui.horizontal(|ui| {
             ui.visuals_mut().widgets.inactive.expansion = -5.; // bug with egui imagebutton padding
@@ -96,11 +365,19 @@ pub fn lobby(
 
             let (mut cam_state, _) = player_cam.single_mut();
 
-            if play_btn.clicked() && lobby_state.selected_map != None {
+            let players = players.filter(|players| !players.is_empty());
+            let ready = lobby_state.selected_map.is_some() && !diagnostics.has_errors();
+            if let (true, true, Some(players)) = (play_btn.clicked(), ready, players) {
                 cam_state.should_pan = true;
                 cam_state.should_zoom = true;
                 ui_state.current_state = UIState::Game;
-                level_manager.current_level = lobby_state.selected_map.clone();
+                commands.insert_resource(MatchConfig {
+                    map: lobby_state.selected_map.clone().unwrap(),
+                    players,
+                    starting: lobby_state.starting.clone(),
+                    rules: lobby_state.rules,
+                    unfilled: lobby_state.unfilled,
+                });
             } else if lobby_state.selected_map.clone() != level_manager.current_level {
                 level_manager.current_level = lobby_state.selected_map.clone();
             }
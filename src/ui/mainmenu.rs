@@ -79,6 +79,12 @@ pub fn main_menu(
             let settings = ui
                 .add(egui::ImageButton::new(images.settings_id, btn_size))
                 .clicked();
+            let replays = ui
+                .add_sized(btn_size, egui::Button::new("Replays"))
+                .clicked();
+            let map_editor = ui
+                .add_sized(btn_size, egui::Button::new("Map Editor"))
+                .clicked();
             let exit = ui
                 .add(egui::ImageButton::new(images.exit_id, btn_size))
                 .clicked();
@@ -96,6 +102,14 @@ pub fn main_menu(
                 ui_state.current_state = UIState::Settings;
             }
 
+            if replays {
+                ui_state.current_state = UIState::Replays;
+            }
+
+            if map_editor {
+                ui_state.current_state = UIState::MapEditor;
+            }
+
             if exit {
                 exit_writer.send(AppExit);
             }
@@ -1,8 +1,11 @@
+mod editor;
 mod game;
 mod gamelobby;
 mod profile;
+mod replay;
 mod mainmenu;
 mod settingsmenu;
+mod trade;
 
 pub mod mousecursor_egui;
 pub mod phage_select;
@@ -13,8 +16,12 @@ use iyes_loopless::prelude::*;
 use leafwing_input_manager::prelude::ActionState;
 
 use crate::game::controller::PlayerAction;
+use crate::game::gamerunner::{GameMoveEvent, GameRunnerEvent};
+use crate::game::LocalPlayerRes;
 
-use self::{gamelobby::LobbyStateRes, settingsmenu::binding_window_system};
+use self::{
+    editor::EditorStateRes, gamelobby::LobbyStateRes, settingsmenu::binding_window_system,
+};
 
 pub fn ui_example(
     mut egui_context: ResMut<EguiContext>,
@@ -36,7 +43,10 @@ pub enum UIState {
     JoinLobby,
     JoiningLobby { lobby_id: String },
     Game,
-    Profile
+    Profile,
+    Replays,
+    ReplayViewer,
+    MapEditor,
 }
 
 pub struct UIStateRes {
@@ -49,12 +59,23 @@ impl Plugin for UIStatePlugin {
         app.insert_resource(UIStateRes {
             current_state: UIState::MainMenu,
         })
-        .insert_resource(LobbyStateRes { selected_map: None })
+        .init_resource::<LobbyStateRes>()
+        .init_resource::<EditorStateRes>()
+        .init_resource::<LocalPlayerRes>()
+        .add_event::<GameRunnerEvent>()
+        .add_event::<GameMoveEvent>()
         .add_system(self::mainmenu::main_menu.run_if(show_main_menu))
         .add_system(self::settingsmenu::controls_window.run_if(show_settings_menu))
         .add_system(self::gamelobby::lobby.run_if(show_lobby_screen))
         .add_system(self::game::game_hud.run_if(show_game_hud))
+        .add_system(self::game::game_over.run_if(show_game_hud))
+        .add_system(self::game::action_notices.run_if(show_game_hud))
+        .add_system(self::trade::trade_panel.run_if(show_game_hud))
         .add_system(self::profile::profile.run_if(show_profile_screen))
+        .add_system(self::replay::replay_browser.run_if(show_replay_browser))
+        .add_system(self::replay::replay_viewer.run_if(show_replay_viewer))
+        .add_system(self::editor::map_editor.run_if(show_map_editor))
+        .add_system(self::editor::map_editor_input.run_if(show_map_editor))
         .add_system(binding_window_system);
     }
 }
@@ -78,4 +99,16 @@ fn show_game_hud(ui_state: Res<UIStateRes>) -> bool {
 
 fn show_profile_screen(ui_state: Res<UIStateRes>) -> bool {
     matches!(&ui_state.current_state, UIState::Profile)
-}
\ No newline at end of file
+}
+
+fn show_replay_browser(ui_state: Res<UIStateRes>) -> bool {
+    matches!(&ui_state.current_state, UIState::Replays)
+}
+
+fn show_replay_viewer(ui_state: Res<UIStateRes>) -> bool {
+    matches!(&ui_state.current_state, UIState::ReplayViewer)
+}
+
+fn show_map_editor(ui_state: Res<UIStateRes>) -> bool {
+    matches!(&ui_state.current_state, UIState::MapEditor)
+}
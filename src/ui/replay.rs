@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    game::{
+        replay::{list_replays, Replay, ReplayPlaybackRes},
+        LevelManagerRes,
+    },
+    util::{
+        camera::{CameraState, PlayerCamMarker},
+        ui::set_ui_style,
+    },
+};
+
+use super::{UIState, UIStateRes};
+
+/// Lists saved replays and loads the chosen one into the viewer
+pub fn replay_browser(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut ui_state: ResMut<UIStateRes>,
+    mut level_manager: ResMut<LevelManagerRes>,
+    mut player_cam: Query<(&mut CameraState, &PlayerCamMarker)>,
+    mut replays: Local<Option<Vec<PathBuf>>>,
+    mut load_error: Local<Option<String>>,
+) {
+    let replays = replays.get_or_insert_with(list_replays);
+
+    egui::Window::new("Replays")
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, -50.0))
+        .resizable(false)
+        .collapsible(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            set_ui_style(ui);
+
+            if replays.is_empty() {
+                ui.label("No replays have been recorded yet");
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for path in replays.iter() {
+                    let name = path
+                        .file_stem()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    if !ui.button(name).clicked() {
+                        continue;
+                    }
+
+                    match Replay::load(path) {
+                        Ok(replay) => match replay.config.as_ref().map(|config| config.map.clone())
+                        {
+                            Some(map) => {
+                                let (mut cam_state, _) = player_cam.single_mut();
+                                cam_state.should_pan = true;
+                                cam_state.should_zoom = true;
+
+                                level_manager.current_level = Some(map);
+                                commands.insert_resource(ReplayPlaybackRes::new(replay));
+                                ui_state.current_state = UIState::ReplayViewer;
+                                *load_error = None;
+                            }
+                            None => *load_error = Some("Replay does not name its map".to_string()),
+                        },
+                        Err(err) => *load_error = Some(err.to_string()),
+                    }
+                }
+            });
+
+            if let Some(err) = load_error.as_ref() {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Refresh").clicked() {
+                    *replays = list_replays();
+                }
+                if ui.button("Back").clicked() {
+                    ui_state.current_state = UIState::MainMenu;
+                }
+            });
+        });
+}
+
+/// Seek, pause and speed controls for the replay being watched
+pub fn replay_viewer(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut ui_state: ResMut<UIStateRes>,
+    mut level_manager: ResMut<LevelManagerRes>,
+    mut player_cam: Query<(&mut CameraState, &PlayerCamMarker)>,
+    playback: Option<ResMut<ReplayPlaybackRes>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    egui::Window::new("Replay")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -16.0))
+        .resizable(false)
+        .collapsible(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            let mut position = playback.position;
+            let len = playback.len();
+            ui.add(egui::Slider::new(&mut position, 0..=len).text("Phase"));
+            if position != playback.position {
+                playback.seek(position);
+            }
+
+            if let Some(phase) = playback.desynced_at {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("Replay diverged from the recording at phase {}", phase),
+                );
+            }
+
+            ui.horizontal(|ui| {
+                let label = if playback.playing { "Pause" } else { "Play" };
+                if ui.button(label).clicked() {
+                    playback.playing = !playback.playing;
+                }
+                if ui.button("Step").clicked() {
+                    playback.playing = false;
+                    playback.step();
+                }
+
+                ui.add(
+                    egui::Slider::new(&mut playback.speed, 0.5..=8.)
+                        .logarithmic(true)
+                        .text("Speed"),
+                );
+
+                if ui.button("Exit").clicked() {
+                    let (mut cam_state, _) = player_cam.single_mut();
+                    cam_state.should_pan = false;
+                    cam_state.should_zoom = false;
+
+                    level_manager.current_level = None;
+                    commands.remove_resource::<ReplayPlaybackRes>();
+                    ui_state.current_state = UIState::MainMenu;
+                }
+            });
+        });
+}
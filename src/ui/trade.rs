@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::game::{
+    controller::PlayerAction, gamerunner::GameMoveEvent, matchconfig::MatchConfig,
+    transmission::PendingOrders, Force, GameMove, GameState, LocalPlayerRes,
+};
+
+#[derive(Default)]
+pub struct TradePanel {
+    open: bool,
+    dest: Option<Force>,
+    qty: u32,
+}
+
+/// Lets the local player offer qubits to other forces and accept offers made to them
+#[allow(clippy::too_many_arguments)]
+pub fn trade_panel(
+    mut egui_context: ResMut<EguiContext>,
+    actions: Query<&ActionState<PlayerAction>>,
+    local_player: Res<LocalPlayerRes>,
+    config: Option<Res<MatchConfig>>,
+    maps: Query<&GameState>,
+    pending_orders: Option<Res<PendingOrders>>,
+    mut game_moves: EventWriter<GameMoveEvent>,
+    mut panel: Local<TradePanel>,
+) {
+    if let Ok(actions) = actions.get_single() {
+        if actions.just_pressed(PlayerAction::OpenQubitTradePanel) {
+            panel.open = !panel.open;
+        }
+    }
+
+    let map = match maps.get_single() {
+        Ok(map) => map,
+        Err(_) => return,
+    };
+
+    let force = local_player.force;
+    let available = map.qubits(force);
+
+    //Forces taking part in the match, the map's spawn slots are already narrowed down to
+    //them once the match has been set up
+    let forces = match config {
+        Some(config) => config.forces(),
+        None => map.spawn_slots.iter().map(|slot| slot.force).collect(),
+    };
+    let recipients: Vec<Force> = forces
+        .into_iter()
+        .filter(|dest| *dest != force && !dest.is_neutral())
+        .filter(|dest| map.trade_rules.allied(force, *dest))
+        .collect();
+    let mut open = panel.open;
+
+    egui::Window::new("Qubit Trade")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(format!("Available qubits: {}", available));
+
+            egui::ComboBox::from_label("Give to")
+                .selected_text(match panel.dest {
+                    Some(dest) => format!("Player {}", dest.0 + 1),
+                    None => "Nobody".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    for dest in recipients {
+                        ui.selectable_value(
+                            &mut panel.dest,
+                            Some(dest),
+                            format!("Player {}", dest.0 + 1),
+                        );
+                    }
+                });
+
+            ui.add(egui::Slider::new(&mut panel.qty, 0..=available).text("Qubits"));
+
+            if let Some(dest) = panel.dest {
+                if ui.button("Offer").clicked() && panel.qty > 0 {
+                    game_moves.send(GameMoveEvent {
+                        force,
+                        game_move: GameMove::GiveQubits {
+                            source: force,
+                            dest,
+                            qty: panel.qty,
+                        },
+                    });
+                    panel.qty = 0;
+                }
+            }
+
+            let offers = pending_orders
+                .iter()
+                .flat_map(|orders| orders.trades.iter());
+            for offer in offers.filter(|offer| offer.dest == force && !offer.accepted) {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Player {} offers {} qubits",
+                        offer.source.0 + 1,
+                        offer.qty
+                    ));
+                    if ui.button("Accept").clicked() {
+                        game_moves.send(GameMoveEvent {
+                            force,
+                            game_move: GameMove::AcceptQubits {
+                                source: offer.source,
+                            },
+                        });
+                    }
+                });
+            }
+        });
+
+    panel.open = open;
+}
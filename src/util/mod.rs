@@ -1,12 +1,20 @@
-use bevy::prelude::{Color, Plugin};
+use bevy::{
+    asset::HandleId,
+    prelude::{AssetEvent, AssetServer, Color, EventReader, HandleUntyped, Plugin, Res, ResMut},
+};
+
+use crate::game::GameState;
 
 use self::{modelloading::ModelPlugin, mouse::MousePlugin};
 pub mod camera;
 pub mod modelloading;
 pub mod mouse;
 pub mod ui;
+#[derive(Default)]
 pub struct MapManifest {
     pub map_files: Vec<String>,
+    /// Keeps every map in `assets/maps` loaded so the asset server reports changes to them
+    handles: Vec<HandleUntyped>,
 }
 pub struct MacroUtils {}
 
@@ -46,24 +54,60 @@ impl From<ColorPalette> for Color {
 
 impl Plugin for MacroUtils {
     fn build(&self, app: &mut bevy::prelude::App) {
-        let maps_dir =
-            std::fs::read_dir("./assets/maps/").expect("Could not read files in ./assets/maps/");
-        let mut map_files: Vec<String> = vec![];
-
-        for map_file in maps_dir {
-            map_files.push(
-                map_file
-                    .expect("Weird thing when iterating over map directory entries")
-                    .file_name()
-                    .into_string()
-                    .expect("Could not convert OS String to string"),
-            );
-        }
-
-        app.insert_resource(MapManifest { map_files })
+        app.init_resource::<MapManifest>()
+            .add_startup_system(load_map_folder)
+            .add_system(update_map_manifest)
             .add_plugin(MousePlugin)
             .add_plugin(ModelPlugin);
     }
 }
 
+/// File name within `assets/maps` of a map loaded by the asset server
+fn map_file_name(asset_server: &AssetServer, handle: impl Into<HandleId>) -> Option<String> {
+    let path = asset_server.get_handle_path(handle)?;
+    let file_name = path.path().strip_prefix("maps").ok()?.to_str()?;
+    Some(file_name.to_string())
+}
+
+/// Lists every file in `assets/maps`, including maps that fail to load so the lobby can
+/// show why
+fn load_map_folder(asset_server: Res<AssetServer>, mut manifest: ResMut<MapManifest>) {
+    manifest.handles = asset_server
+        .load_folder("maps")
+        .expect("Could not read files in ./assets/maps/");
+    let mut map_files: Vec<String> = manifest
+        .handles
+        .iter()
+        .filter_map(|handle| map_file_name(&asset_server, handle))
+        .collect();
+    map_files.sort();
+    manifest.map_files = map_files;
+}
+
+/// Keeps the lobby's map list in step with the maps the asset server loads and unloads
+fn update_map_manifest(
+    asset_server: Res<AssetServer>,
+    mut map_events: EventReader<AssetEvent<GameState>>,
+    mut manifest: ResMut<MapManifest>,
+) {
+    for event in map_events.iter() {
+        let (handle, removed) = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => (handle, false),
+            AssetEvent::Removed { handle } => (handle, true),
+        };
+        let file_name = match map_file_name(&asset_server, handle) {
+            Some(file_name) => file_name,
+            None => continue,
+        };
+
+        match (manifest.map_files.binary_search(&file_name), removed) {
+            (Ok(index), true) => {
+                manifest.map_files.remove(index);
+            }
+            (Err(index), false) => manifest.map_files.insert(index, file_name),
+            _ => {}
+        }
+    }
+}
+
 impl MacroUtils {}
@@ -3,6 +3,11 @@ use bevy_asset_loader::prelude::*;
 use bevy_mod_picking::{PickableBundle, PickingEvent};
 use iyes_progress::{ProgressCounter, ProgressPlugin};
 
+use crate::game::{
+    visibility::{visible_state, FogOfWarRes},
+    GameState, LocalPlayerRes, NodeId, NodeTenant,
+};
+
 pub struct ModelPlugin;
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
@@ -25,6 +30,8 @@ impl Plugin for ModelPlugin {
             //ProgressPlugin::new(LoadingStates::AssetLoading).continue_to(LoadingStates::Loaded),
             //)
             //.add_system(model_loading_progress)
+            .add_system(sync_visible_nodes)
+            .add_system(occupant_models.after(sync_visible_nodes))
             .add_system(model_coloring)
             .add_startup_system(load_gltfs);
     }
@@ -54,6 +61,8 @@ pub struct NodeTenentAssets {
     pub nexus: Handle<Scene>,
     #[asset(path = "3DArt/Cell/Cell.gltf#Scene0")]
     pub cell: Handle<Scene>,
+    #[asset(path = "3DArt/Recombinators/Recombinator.gltf#Scene0")]
+    pub recombinator: Handle<Scene>,
     #[asset(path = "3DArt/Cell Variations/Cell Var 1.gltf#Scene0")]
     pub cell_var_1: Handle<Scene>,
     #[asset(path = "3DArt/Cell Variations/Cell Var 2.gltf#Scene0")]
@@ -63,14 +72,113 @@ pub struct NodeTenentAssets {
     //cell_var_3: Handle<Scene>,
 }
 
+/// Glow of nodes the local force cannot currently see
+const FOGGED_EMISSIVE: Color = Color::rgb(0.05, 0.05, 0.05);
+
+/// Phage model standing on the node it occupies, a child of that node's model
+#[derive(Component)]
+pub struct OccupantModel(NodeId);
+
+/// Keeps the node components the client renders from in step with what the local force
+/// is allowed to know of the map
+pub fn sync_visible_nodes(
+    maps: Query<&GameState>,
+    changed_maps: Query<(), Changed<GameState>>,
+    fog: Res<FogOfWarRes>,
+    local_player: Res<LocalPlayerRes>,
+    mut nodes: Query<&mut crate::game::Node>,
+) {
+    if changed_maps.is_empty() && !fog.is_changed() && !local_player.is_changed() {
+        return;
+    }
+    let map = match maps.get_single() {
+        Ok(map) => map,
+        Err(_) => return,
+    };
+
+    let visible = match fog.visible {
+        Some(_) => visible_state(map, local_player.force),
+        None => map.clone(),
+    };
+    for mut node in &mut nodes {
+        match visible.nodes.get(&node.id) {
+            //Only write on a change so occupant models can rely on change detection
+            Some(seen) if *seen != *node => *node = seen.clone(),
+            _ => {}
+        }
+    }
+}
+
+/// Stands a phage model on every node with an occupant the local force can see
+pub fn occupant_models(
+    mut commands: Commands,
+    models: Option<Res<ModelAssets>>,
+    nodes: Query<(&crate::game::Node, &Parent), Changed<crate::game::Node>>,
+    mut occupants: Query<(Entity, &OccupantModel, &mut Visibility)>,
+    fog: Res<FogOfWarRes>,
+) {
+    let models = match models {
+        Some(models) => models,
+        None => return,
+    };
+
+    for (node, parent) in &nodes {
+        let model = occupants
+            .iter()
+            .find(|(_, occupant, _)| occupant.0 == node.id)
+            .map(|(entity, _, _)| entity);
+        match (node.occupant(), model) {
+            (Some(_), None) => {
+                let model = commands
+                    .spawn_bundle(SceneBundle {
+                        scene: models.avatar_phage.clone(),
+                        transform: Transform::from_xyz(0., 1., 0.),
+                        visibility: Visibility {
+                            is_visible: fog.shows(node.id),
+                        },
+                        ..default()
+                    })
+                    .insert(OccupantModel(node.id))
+                    .insert(Name::new("Occupant"))
+                    .id();
+                commands.entity(parent.get()).add_child(model);
+            }
+            (None, Some(model)) => commands.entity(model).despawn_recursive(),
+            _ => {}
+        }
+    }
+
+    //Phage on fogged nodes are hidden rather than ghosted, where they are is the secret
+    if fog.is_changed() {
+        for (_, occupant, mut visibility) in &mut occupants {
+            visibility.is_visible = fog.shows(occupant.0);
+        }
+    }
+}
+
 pub fn model_coloring(
     nodes: Query<(&crate::game::Node, &Parent)>,
     parents: Query<&Children>,
+    occupants: Query<(), With<OccupantModel>>,
     mut mat_handles: Query<&mut Handle<StandardMaterial>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut visibilities: Query<&mut Visibility>,
+    fog: Res<FogOfWarRes>,
 ) {
     for (node, parent) in &nodes {
-        let color = node.force.color();
+        //Fogged nodes are ghosted, except recombinators which give away too much to be seen
+        let visible = fog.shows(node.id);
+        if let Ok(mut visibility) = visibilities.get_mut(parent.get()) {
+            let hidden = !visible && matches!(node.tenant, NodeTenant::Recombinator { .. });
+            if visibility.is_visible == hidden {
+                visibility.is_visible = !hidden;
+            }
+        }
+
+        let color = match visible {
+            true => node.force.color(),
+            false => FOGGED_EMISSIVE,
+        };
         //If I am your parent then I must have children
         let children = parents.get(parent.get()).unwrap();
         //Crawl through children until you find one with the material
@@ -84,7 +192,10 @@ pub fn model_coloring(
                 mat.emissive = color;
                 *material = materials.add(mat);
             }
-            //Add my children to the list to search
+            //Add my children to the list to search, occupants are not part of the node
+            if occupants.contains(*checking) {
+                continue;
+            }
             if let Ok(my_children) = parents.get(*checking) {
                 let mut my_children: Vec<&Entity> = my_children.iter().collect();
                 children.append(&mut my_children);
@@ -130,6 +241,7 @@ fn load_gltfs(mut commands: Commands, asset_server: Res<AssetServer>) {
         replicator: asset_server.load("3DArt/Replicator/Replicator.gltf#Scene0"),
         nexus: asset_server.load("3DArt/Nexus/Nexus.gltf#Scene0"),
         cell: asset_server.load("3DArt/Cell/Cell.glb#Scene0"),
+        recombinator: asset_server.load("3DArt/Recombinators/Recombinator.gltf#Scene0"),
         cell_var_1: asset_server.load("3DArt/Cell Variations/Cell Var 1.gltf#Scene0"),
         cell_var_2: asset_server.load("3DArt/Cell Variations/Cell Var 2.gltf#Scene0"),
     });
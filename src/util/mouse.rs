@@ -5,7 +5,8 @@ pub struct MousePlugin;
 #[derive(Debug, Default)]
 pub struct MousePosition {
     pub ndc: Vec2,
-    //May be inaccurate :( untested
+    /// Where the cursor points at the ground plane (y = 0), or the point under the
+    /// cursor on the near plane when looking away from the ground
     pub world: Vec3,
 }
 
@@ -48,7 +49,17 @@ fn mouse_position(
                     camera_transform.compute_matrix() * camera.projection_matrix().inverse();
 
                 // use it to convert ndc to world-space coordinates
-                let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
+                let near = ndc_to_world.project_point3(ndc.extend(-1.0));
+                let far = ndc_to_world.project_point3(ndc.extend(1.0));
+
+                // follow the ray through the cursor down to the ground plane
+                let direction = far - near;
+                let world_pos = match -near.y / direction.y {
+                    distance if distance.is_finite() && distance >= 0.0 => {
+                        near + direction * distance
+                    }
+                    _ => near,
+                };
 
                 mouse_position.ndc = ndc;
                 mouse_position.world = world_pos;